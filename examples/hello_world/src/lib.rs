@@ -88,13 +88,11 @@ impl HTTPModule for Module {
     unsafe extern "C" fn postconfiguration(cf: *mut ngx_conf_t) -> ngx_int_t {
         let cmcf = ngx_http_conf_get_module_main_conf(cf, &ngx_http_core_module) as *mut ngx_http_core_main_conf_t;
 
-        let h = ngx_array_push(&mut (*cmcf).phases[ngx_http_phases_NGX_HTTP_ACCESS_PHASE as usize].handlers) as *mut ngx_http_handler_pt;
-        if h.is_null() {
+        let handlers = NgxArray::<ngx_http_handler_pt>::from_ngx_array(&mut (*cmcf).phases[ngx_http_phases_NGX_HTTP_ACCESS_PHASE as usize].handlers);
+        if handlers.push(Some(ngx_http_hello_world_access_handler)).is_none() {
             return ERROR.into();
         }
 
-        *h = Some(ngx_http_hello_world_access_handler);
-
         OK.into()
     }
 }
@@ -124,8 +122,8 @@ unsafe extern "C" fn ngx_http_hello_world(cf: *mut ngx_conf_t, _cmd: *mut ngx_co
 #[no_mangle]
 unsafe extern "C" fn ngx_http_hello_world_set_text(cf: *mut ngx_conf_t, _cmd: *mut ngx_command_t, conf: *mut c_void) -> *mut c_char {
     let conf = &mut *(conf as *mut LocConf);
-    let args = (*(*cf).args).elts as *mut ngx_str_t;
-    let value = NgxStr::from_ngx_str(*args.add(1));
+    let args = NgxArray::<ngx_str_t>::from_ngx_array((*cf).args).as_slice();
+    let value = NgxStr::from_ngx_str(args[1]);
     conf.text = String::from(value.to_string_lossy());
 
     ptr::null_mut()
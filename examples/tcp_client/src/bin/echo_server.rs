@@ -0,0 +1,33 @@
+//! Line based echo server on 127.0.0.1:9000, the backend of the `tcp_client` example.
+//!
+//! Each new connection is logged, so that reused (keepalive) connections can be told apart.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+fn main() -> std::io::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:9000")?;
+    println!("listening on {}", listener.local_addr()?);
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        println!("accepted connection from {}", stream.peer_addr()?);
+        thread::spawn(move || {
+            if let Err(err) = echo(stream) {
+                eprintln!("connection failed: {}", err);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+fn echo(stream: TcpStream) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        writer.write_all(format!("echo: {}\n", line?).as_bytes())?;
+    }
+
+    Ok(())
+}
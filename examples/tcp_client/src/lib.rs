@@ -0,0 +1,192 @@
+use nginx_rs::bindings::*;
+use nginx_rs::core::*;
+use nginx_rs::event::*;
+use nginx_rs::http::*;
+
+use nginx_rs::{ngx_modules, ngx_string, http_async_request_handler, ngx_null_command};
+
+use std::net::SocketAddr;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::time::Duration;
+
+#[no_mangle]
+static mut ngx_http_tcp_client_commands: [ngx_command_t; 2] = [
+    ngx_command_t {
+        name: ngx_string!("tcp_client_pass"),
+        type_: (NGX_HTTP_LOC_CONF|NGX_CONF_TAKE1) as ngx_uint_t,
+        set: Some(ngx_http_tcp_client_pass),
+        conf: NGX_RS_HTTP_LOC_CONF_OFFSET,
+        offset: 0,
+        post: ptr::null_mut(),
+    },
+    ngx_null_command!(),
+];
+
+#[no_mangle]
+static ngx_http_tcp_client_module_ctx: ngx_http_module_t = ngx_http_module_t {
+    preconfiguration: Some(Module::preconfiguration),
+    postconfiguration: Some(Module::postconfiguration),
+
+    create_main_conf: Some(Module::create_main_conf),
+    init_main_conf: Some(Module::init_main_conf),
+
+    create_srv_conf: Some(Module::create_srv_conf),
+    merge_srv_conf: Some(Module::merge_srv_conf),
+
+    create_loc_conf: Some(Module::create_loc_conf),
+    merge_loc_conf: Some(Module::merge_loc_conf),
+};
+
+#[no_mangle]
+pub static mut ngx_http_tcp_client_module: ngx_module_t = ngx_module_t {
+    ctx_index: ngx_uint_t::max_value(),
+    index: ngx_uint_t::max_value(),
+    name: ptr::null_mut(),
+    spare0: 0,
+    spare1: 0,
+    version: nginx_version as ngx_uint_t,
+    signature: NGX_RS_MODULE_SIGNATURE.as_ptr() as *const c_char,
+
+    ctx: &ngx_http_tcp_client_module_ctx as *const _ as *mut _,
+    commands: unsafe { &ngx_http_tcp_client_commands[0] as *const _ as *mut _ },
+    type_: NGX_HTTP_MODULE as ngx_uint_t,
+
+    init_master: None,
+    init_module: None,
+    init_process: Some(Module::init_process),
+    init_thread: None,
+    exit_thread: None,
+    exit_process: None,
+    exit_master: None,
+
+    spare_hook0: 0,
+    spare_hook1: 0,
+    spare_hook2: 0,
+    spare_hook3: 0,
+    spare_hook4: 0,
+    spare_hook5: 0,
+    spare_hook6: 0,
+    spare_hook7: 0,
+};
+
+ngx_modules!(ngx_http_tcp_client_module);
+
+struct Module;
+
+impl HTTPModule for Module {
+    type MainConf = ();
+    type SrvConf = ();
+    type LocConf = LocConf;
+}
+
+impl ModuleLifecycle for Module {
+    fn on_init_process(_cycle: &mut Cycle) -> Status {
+        // Keep a few idle connections to the backend in each worker
+        AsyncTcpStream::set_keepalive_limit(8);
+        OK
+    }
+}
+
+#[derive(Default)]
+struct LocConf {
+    addr: Option<SocketAddr>,
+}
+
+impl Merge for LocConf {
+    fn merge(&mut self, prev: &LocConf) {
+        if self.addr.is_none() {
+            self.addr = prev.addr;
+        }
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn ngx_http_tcp_client_pass(cf: *mut ngx_conf_t, _cmd: *mut ngx_command_t, conf: *mut c_void) -> *mut c_char {
+    let conf = &mut *(conf as *mut LocConf);
+    let args = NgxArray::<ngx_str_t>::from_ngx_array((*cf).args).as_slice();
+    let value = NgxStr::from_ngx_str(args[1]);
+
+    conf.addr = match value.to_str().ok().and_then(|addr| addr.parse().ok()) {
+        Some(addr) => Some(addr),
+        None => return "has an invalid address\0".as_ptr() as *mut c_char,
+    };
+
+    let clcf = ngx_http_conf_get_module_loc_conf(cf, &ngx_http_core_module) as *mut ngx_http_core_loc_conf_t;
+    (*clcf).handler = Some(ngx_http_tcp_client_handler);
+
+    ptr::null_mut()
+}
+
+/// Send the request URI to the backend, and respond with the line it sends back.
+async fn exchange(addr: SocketAddr, uri: String) -> std::io::Result<Vec<u8>> {
+    let mut stream = AsyncTcpStream::connect(addr, Duration::from_secs(1)).await?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)));
+    stream.set_write_timeout(Some(Duration::from_secs(5)));
+
+    stream.write_all(format!("{}\n", uri).as_bytes()).await?;
+
+    let mut line = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !line.ends_with(b"\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        line.extend_from_slice(&buf[..n]);
+    }
+
+    // The whole response was read, so the connection can be reused
+    stream.keepalive(Duration::from_secs(60));
+
+    Ok(line)
+}
+
+http_async_request_handler!(ngx_http_tcp_client_handler, |mut request: AsyncRequest| async move {
+    // The request is valid while the handler's task runs
+    let request = match request.get_mut() {
+        Some(request) => request,
+        None => return ERROR,
+    };
+
+    // Ignore client request body if any
+    if !request.discard_request_body().is_ok() {
+        return HTTP_INTERNAL_SERVER_ERROR.into();
+    }
+
+    let hlcf = request.get_module_loc_conf(&ngx_http_tcp_client_module) as *mut LocConf;
+    let addr = match unsafe { (*hlcf).addr } {
+        Some(addr) => addr,
+        None => return HTTP_INTERNAL_SERVER_ERROR.into(),
+    };
+
+    let uri = request.uri().to_string_lossy().into_owned();
+    let body = match exchange(addr, uri).await {
+        Ok(body) => body,
+        Err(_) => return HTTP_BAD_GATEWAY.into(),
+    };
+
+    // Send header
+    request.set_status(HTTP_OK);
+    request.set_content_length_n(body.len());
+    let status = request.send_header();
+    if status == ERROR || status > OK || request.header_only() {
+        return status;
+    }
+
+    // Send body
+    let mut buf = match request.pool().create_buffer(body.len()) {
+        Some(buf) => buf,
+        None => return HTTP_INTERNAL_SERVER_ERROR.into(),
+    };
+    unsafe {
+        let b = buf.as_ngx_buf_mut();
+        ptr::copy_nonoverlapping(body.as_ptr(), (*b).pos, body.len());
+        (*b).last = (*b).pos.add(body.len());
+    }
+    buf.set_last_buf(request.is_main());
+    buf.set_last_in_chain(true);
+
+    let mut out = ngx_chain_t { buf: buf.as_ngx_buf_mut(), next: ptr::null_mut() };
+    request.output_filter(&mut out)
+});
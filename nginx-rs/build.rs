@@ -1,37 +1,96 @@
 extern crate bindgen;
 
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::mem;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 fn main() {
     // Tell cargo to tell rustc to link the system bzip2
     // shared library.
     println!("cargo:rustc-link-lib=bz2");
 
-    let nginx_dir = env::var("NGINX_DIR").unwrap_or(String::from("../../nginx"));
+    println!("cargo:rerun-if-changed=wrapper.h");
+    for var in &["NGINX_DIR", "NGINX_CONFIGURE_ARGS", "NGINX_AUTO_CONFIGURE", "OPENSSL_INCLUDE_DIR"] {
+        println!("cargo:rerun-if-env-changed={}", var);
+    }
+
+    let nginx_dir = PathBuf::from(env::var("NGINX_DIR").unwrap_or(String::from("../../nginx")));
+    if !nginx_dir.join("src/core/nginx.h").exists() {
+        panic!(
+            "Nginx sources not found in `{}`. Set NGINX_DIR to a checkout of the Nginx sources.",
+            nginx_dir.display()
+        );
+    }
+
+    let (source_dir, objs_dir) = configured_dirs(&nginx_dir);
+    let auto_config_path = objs_dir.join("ngx_auto_config.h");
+    let auto_config = fs::read_to_string(&auto_config_path).unwrap_or_else(|err| panic!(
+        "Unable to read `{}`: {}. Configure Nginx with `auto/configure --with-compat` first.",
+        auto_config_path.display(),
+        err
+    ));
+    let include_dirs = include_dirs(&source_dir, &objs_dir);
+
+    // Reconfiguring Nginx changes the available modules and their headers
+    println!("cargo:rerun-if-changed={}", auto_config_path.display());
+    println!("cargo:rerun-if-changed={}", objs_dir.join("Makefile").display());
+
+    if !auto_config.contains("#define NGX_COMPAT") {
+        println!("cargo:warning=Nginx is not configured with --with-compat, modules may fail to load");
+    }
+
+    if feature_enabled("SSL") && !auto_config.contains("#define NGX_SSL") {
+        panic!("The `ssl` feature requires Nginx configured with --with-http_ssl_module");
+    }
+
+    if feature_enabled("THREAD_POOL") && !auto_config.contains("#define NGX_THREADS") {
+        panic!("The `thread_pool` feature requires Nginx configured with --with-threads");
+    }
 
     // The bindgen::Builder is the main entry point
     // to bindgen, and lets you build up options for
     // the resulting bindings.
-    let bindings = bindgen::Builder::default()
+    let mut builder = bindgen::Builder::default()
         // The input header we would like to generate
         // bindings for.
         .header("wrapper.h")
         .layout_tests(false)
         .whitelist_type("ngx_.*")
         .whitelist_function("ngx_.*")
-        .whitelist_var("NGX_.*|ngx_.*|nginx_.*")
-        .clang_arg(format!("-I{}/src/core", nginx_dir))
-        .clang_arg(format!("-I{}/src/event", nginx_dir))
-        .clang_arg(format!("-I{}/src/event/modules", nginx_dir))
-        .clang_arg(format!("-I{}/src/os/unix", nginx_dir))
-        .clang_arg(format!("-I{}/objs", nginx_dir))
-        .clang_arg(format!("-I{}/src/http", nginx_dir))
-        .clang_arg(format!("-I{}/src/http/modules", nginx_dir))
+        .whitelist_var("NGX_.*|ngx_.*|nginx_.*");
+
+    for dir in &include_dirs {
+        builder = builder.clang_arg(format!("-I{}", dir.display()));
+    }
+
+    if feature_enabled("SSL") {
+        // OpenSSL headers are needed to parse `ngx_event_openssl.h`
+        if let Ok(openssl_include_dir) = env::var("OPENSSL_INCLUDE_DIR") {
+            builder = builder.clang_arg(format!("-I{}", openssl_include_dir));
+        }
+    }
+
+    if feature_enabled("STREAM") {
+        require_module(&include_dirs, "src/stream", "stream", "--with-stream");
+        builder = builder.clang_arg("-DNGX_RS_STREAM");
+    }
+
+    if feature_enabled("MAIL") {
+        require_module(&include_dirs, "src/mail", "mail", "--with-mail");
+        builder = builder.clang_arg("-DNGX_RS_MAIL");
+    }
+
+    let bindings = builder
         // Finish the builder and generate the bindings.
         .generate()
-        // Unwrap the Result and panic on failure.
-        .expect("Unable to generate bindings");
+        .unwrap_or_else(|_| panic!(
+            "Unable to generate bindings from the Nginx headers in `{}`. Check that the headers of \
+             the libraries Nginx is configured with (e.g. OpenSSL or PCRE) are installed.",
+            nginx_dir.display()
+        ));
 
     // Write the bindings to the $OUT_DIR/bindings.rs file.
     let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
@@ -39,3 +98,197 @@ fn main() {
         .write_to_file(out_path.join("bindings.rs"))
         .expect("Couldn't write bindings!");
 }
+
+fn feature_enabled(feature: &str) -> bool {
+    env::var(format!("CARGO_FEATURE_{}", feature)).is_ok()
+}
+
+/// Find the configured Nginx sources and the output of `auto/configure` in them, configuring
+/// Nginx if needed.
+///
+/// `objs` in the Nginx sources is used if it exists. Otherwise, if `NGINX_AUTO_CONFIGURE` or
+/// `NGINX_CONFIGURE_ARGS` is set, Nginx is configured with `--with-compat` and the arguments from
+/// `NGINX_CONFIGURE_ARGS` in `OUT_DIR`, leaving the Nginx sources untouched.
+fn configured_dirs(nginx_dir: &Path) -> (PathBuf, PathBuf) {
+    let objs_dir = nginx_dir.join("objs");
+    if objs_dir.join("ngx_auto_config.h").exists() {
+        return (nginx_dir.to_path_buf(), objs_dir);
+    }
+
+    let configure_args = env::var("NGINX_CONFIGURE_ARGS").ok();
+    if configure_args.is_none() && env::var("NGINX_AUTO_CONFIGURE").is_err() {
+        panic!(
+            "`{}` not found. Configure Nginx with `auto/configure --with-compat` first, or set \
+             NGINX_AUTO_CONFIGURE=1 (or NGINX_CONFIGURE_ARGS) to configure it automatically.",
+            objs_dir.join("ngx_auto_config.h").display()
+        );
+    }
+
+    let configure_args = configure_args.unwrap_or_default();
+    let source_dir = PathBuf::from(env::var("OUT_DIR").unwrap()).join("nginx");
+    let objs_dir = source_dir.join("objs");
+    let stamp = objs_dir.join("configure-stamp");
+
+    // Only reconfigure if the Nginx sources or the arguments have changed
+    let nginx_dir = fs::canonicalize(nginx_dir).expect("Couldn't resolve the Nginx sources!");
+    let configure_stamp = format!("{}\n{}\n", nginx_dir.display(), configure_args);
+    if objs_dir.join("ngx_auto_config.h").exists() && fs::read_to_string(&stamp).ok().as_ref() == Some(&configure_stamp) {
+        return (source_dir, objs_dir);
+    }
+
+    // Start from a fresh tree, since links and `objs` may come from other Nginx sources
+    if source_dir.exists() {
+        fs::remove_dir_all(&source_dir).expect("Couldn't remove the previous Nginx build directory!");
+    }
+    link_sources(&nginx_dir, &source_dir);
+
+    // `auto/configure` writes the top-level `Makefile` into its working directory, so it's run
+    // in a tree of links to the Nginx sources
+    let status = Command::new("auto/configure")
+        .current_dir(&source_dir)
+        .arg("--with-compat")
+        .args(shell_words(&configure_args))
+        .arg("--builddir=objs")
+        .status()
+        .unwrap_or_else(|err| panic!("Unable to run `auto/configure` in `{}`: {}", source_dir.display(), err));
+
+    if !status.success() {
+        panic!("`auto/configure --with-compat {}` failed with {}", configure_args, status);
+    }
+
+    fs::write(&stamp, &configure_stamp).expect("Couldn't write configure stamp!");
+
+    (source_dir, objs_dir)
+}
+
+/// Link the top-level entries of the Nginx sources into `dest`, except for build output.
+fn link_sources(nginx_dir: &Path, dest: &Path) {
+    fs::create_dir_all(dest).expect("Couldn't create the Nginx build directory!");
+
+    let entries = fs::read_dir(nginx_dir)
+        .unwrap_or_else(|err| panic!("Unable to read `{}`: {}", nginx_dir.display(), err));
+
+    for entry in entries {
+        let entry = entry.expect("Couldn't read the Nginx sources!");
+        let name = entry.file_name();
+        if name == "objs" || name == "Makefile" {
+            continue;
+        }
+
+        let link = dest.join(&name);
+        let target = fs::canonicalize(entry.path()).expect("Couldn't resolve the Nginx sources!");
+        symlink(&target, &link).unwrap_or_else(|err| panic!("Unable to link `{}`: {}", link.display(), err));
+    }
+}
+
+/// Split `NGINX_CONFIGURE_ARGS` into arguments like a POSIX shell, handling quotes and
+/// backslash escapes (e.g. `--with-cc-opt="-O2 -g"`), but not expansions.
+fn shell_words(s: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                word.extend(chars.by_ref().take_while(|&c| c != '\''));
+            },
+            '"' => {
+                in_word = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some(c @ '"') | Some(c @ '\\') | Some(c @ '$') | Some(c @ '`') => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            },
+                            None => word.push('\\'),
+                        },
+                        c => word.push(c),
+                    }
+                }
+            },
+            '\\' => {
+                in_word = true;
+                if let Some(c) = chars.next() {
+                    word.push(c);
+                }
+            },
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(mem::take(&mut word));
+                    in_word = false;
+                }
+            },
+            c => {
+                in_word = true;
+                word.push(c);
+            },
+        }
+    }
+
+    if in_word {
+        words.push(word);
+    }
+
+    words
+}
+
+/// Include directories of the configured Nginx modules.
+///
+/// These are read from `ALL_INCS` in the generated `Makefile`, so that the headers of optional
+/// modules (e.g. stream, mail or third-party modules) are found.
+fn include_dirs(source_dir: &Path, objs_dir: &Path) -> Vec<PathBuf> {
+    let makefile = fs::read_to_string(objs_dir.join("Makefile")).unwrap_or_default();
+
+    let dirs = match parse_all_incs(&makefile) {
+        Some(dirs) => dirs,
+        None => {
+            println!("cargo:warning=ALL_INCS not found in the Nginx Makefile, using the default include directories");
+            ["src/core", "src/event", "src/event/modules", "src/os/unix", "src/http", "src/http/modules"]
+                .iter()
+                .map(PathBuf::from)
+                .chain(Some(objs_dir.to_path_buf()))
+                .collect()
+        },
+    };
+
+    dirs.into_iter()
+        .map(|dir| if dir.is_relative() { source_dir.join(dir) } else { dir })
+        .collect()
+}
+
+/// Parse the (possibly continued) `ALL_INCS = -I dir ...` variable of a Makefile.
+fn parse_all_incs(makefile: &str) -> Option<Vec<PathBuf>> {
+    let mut lines = makefile.lines();
+    let mut line = lines.find(|line| line.starts_with("ALL_INCS"))?;
+    let mut value = line.splitn(2, '=').nth(1)?.to_string();
+
+    while line.trim_end().ends_with('\\') {
+        line = lines.next()?;
+        value.push(' ');
+        value.push_str(line);
+    }
+
+    let mut dirs = Vec::new();
+    let mut args = value.split(|c: char| c.is_whitespace() || c == '\\').filter(|arg| !arg.is_empty());
+    while let Some(arg) = args.next() {
+        if arg == "-I" {
+            dirs.push(PathBuf::from(args.next()?));
+        } else if let Some(dir) = arg.strip_prefix("-I") {
+            dirs.push(PathBuf::from(dir));
+        }
+    }
+
+    Some(dirs)
+}
+
+fn require_module(include_dirs: &[PathBuf], dir: &str, feature: &str, configure_arg: &str) {
+    if !include_dirs.iter().any(|include_dir| include_dir.ends_with(dir)) {
+        panic!("The `{}` feature requires Nginx configured with {}", feature, configure_arg);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::bindings::*;
+
+use std::marker::PhantomData;
+use std::{mem, ptr, slice};
+
+/// Typed view of an Nginx [array] (`ngx_array_t`) of `T`.
+///
+/// Elements are allocated from the array's pool and are never dropped.
+///
+/// [array]: https://nginx.org/en/docs/dev/development_guide.html#array
+#[repr(transparent)]
+pub struct NgxArray<T>(ngx_array_t, PhantomData<T>);
+
+impl<T> NgxArray<T> {
+    /// Create an [`NgxArray`] from an [`ngx_array_t`] whose elements are of type `T`.
+    pub unsafe fn from_ngx_array<'a>(a: *mut ngx_array_t) -> &'a mut NgxArray<T> {
+        assert!(!a.is_null());
+        debug_assert_eq!((*a).size, mem::size_of::<T>());
+        // SAFETY: The caller has provided a valid pointer to an `ngx_array_t` of `T`, which
+        // shares the same representation as `NgxArray<T>`.
+        &mut *a.cast::<NgxArray<T>>()
+    }
+
+    /// Pointer to the underlying [`ngx_array_t`].
+    pub fn as_ngx_array(&mut self) -> *mut ngx_array_t {
+        &mut self.0
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.0.nelts
+    }
+
+    /// Returns `true` if the array is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The elements as a slice.
+    pub fn as_slice(&self) -> &[T] {
+        if self.0.nelts == 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.0.elts as *const T, self.0.nelts) }
+    }
+
+    /// The elements as a mutable slice.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        if self.0.nelts == 0 {
+            return &mut [];
+        }
+        unsafe { slice::from_raw_parts_mut(self.0.elts as *mut T, self.0.nelts) }
+    }
+
+    /// Iterate over the elements.
+    pub fn iter(&self) -> slice::Iter<T> {
+        self.as_slice().iter()
+    }
+
+    /// Iterate mutably over the elements.
+    pub fn iter_mut(&mut self) -> slice::IterMut<T> {
+        self.as_mut_slice().iter_mut()
+    }
+
+    /// Append an element, growing the array if needed.
+    ///
+    /// Returns `None` if memory can not be allocated.
+    pub fn push(&mut self, value: T) -> Option<&mut T> {
+        unsafe {
+            let p = ngx_array_push(&mut self.0) as *mut T;
+            if p.is_null() {
+                return None;
+            }
+            ptr::write(p, value);
+            Some(&mut *p)
+        }
+    }
+}
@@ -91,3 +91,80 @@ impl Buffer for MemoryBuffer {
         self.0
     }
 }
+
+/// Buffer of a chain link (`ngx_chain_t`), e.g. in a filter.
+///
+/// The buffer may be in memory or refer to a file, in which case its bytes are not available.
+pub struct ChainBuffer(*mut ngx_buf_t);
+
+impl ChainBuffer {
+    pub fn from_ngx_buf(buf: *mut ngx_buf_t) -> ChainBuffer {
+        assert!(!buf.is_null());
+        ChainBuffer(buf)
+    }
+
+    /// Is the buffer content in memory (`ngx_buf_in_memory`)?
+    pub fn in_memory(&self) -> bool {
+        unsafe { (*self.0).temporary() != 0 || (*self.0).memory() != 0 || (*self.0).mmap() != 0 }
+    }
+
+    /// Is this a special buffer, only carrying flags such as `flush` or `last_buf`
+    /// (`ngx_buf_special`)?
+    pub fn is_special(&self) -> bool {
+        unsafe {
+            let b = &*self.0;
+            (b.flush() != 0 || b.last_buf() != 0 || b.sync() != 0) && !self.in_memory() && b.in_file() == 0
+        }
+    }
+}
+
+impl Buffer for ChainBuffer {
+    fn as_ngx_buf(&self) -> *const ngx_buf_t {
+        self.0
+    }
+
+    fn as_ngx_buf_mut(&mut self) -> *mut ngx_buf_t {
+        self.0
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        if !self.in_memory() || self.len() == 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts((*self.0).pos, self.len()) }
+    }
+
+    fn len(&self) -> usize {
+        if !self.in_memory() {
+            return 0;
+        }
+        unsafe { usize::wrapping_sub((*self.0).last as _, (*self.0).pos as _) }
+    }
+}
+
+/// Iterate over the buffers of a chain.
+///
+/// The chain must remain valid while the iterator is used.
+pub unsafe fn iter_chain(chain: *mut ngx_chain_t) -> ChainIter {
+    ChainIter(chain)
+}
+
+/// Iterator over the buffers of a chain, created with [`iter_chain`].
+pub struct ChainIter(*mut ngx_chain_t);
+
+impl Iterator for ChainIter {
+    type Item = ChainBuffer;
+
+    fn next(&mut self) -> Option<ChainBuffer> {
+        while !self.0.is_null() {
+            // SAFETY: The chain was valid when the iterator was created.
+            let cl = unsafe { &*self.0 };
+            self.0 = cl.next;
+            if !cl.buf.is_null() {
+                return Some(ChainBuffer::from_ngx_buf(cl.buf));
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,20 @@
+use crate::bindings::*;
+
+use std::os::raw::{c_char, c_void};
+
+// Define here since bindgen can't evaluate these casts
+
+/// Unset value of a `ngx_uint_t` configuration field.
+pub const NGX_CONF_UNSET_UINT: ngx_uint_t = ngx_uint_t::max_value();
+
+/// Unset value of a pointer configuration field.
+pub const NGX_CONF_UNSET_PTR: *mut c_void = -1isize as *mut c_void;
+
+/// Unset value of a `size_t` configuration field.
+pub const NGX_CONF_UNSET_SIZE: usize = usize::max_value();
+
+/// Unset value of a `ngx_msec_t` configuration field.
+pub const NGX_CONF_UNSET_MSEC: ngx_msec_t = ngx_msec_t::max_value();
+
+/// Return value of a configuration handler on failure (when the error was already logged).
+pub const NGX_CONF_ERROR: *mut c_char = -1isize as *mut c_char;
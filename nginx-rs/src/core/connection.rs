@@ -0,0 +1,127 @@
+use crate::bindings::*;
+use crate::core::*;
+
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::{mem, ptr};
+
+/// Wrapper for an Nginx [connection].
+///
+/// [connection]: https://nginx.org/en/docs/dev/development_guide.html#connection
+#[repr(transparent)]
+pub struct Connection(ngx_connection_t);
+
+impl Connection {
+    /// Create a [`Connection`] from an [`ngx_connection_t`].
+    ///
+    /// [`ngx_connection_t`]: https://nginx.org/en/docs/dev/development_guide.html#connection
+    pub unsafe fn from_ngx_connection<'a>(c: *mut ngx_connection_t) -> &'a mut Connection {
+        // SAFETY: The caller has provided a valid non-null pointer to a valid `ngx_connection_t`
+        // which shares the same representation as `Connection`.
+        &mut *c.cast::<Connection>()
+    }
+
+    /// Pointer to the underlying [`ngx_connection_t`].
+    ///
+    /// [`ngx_connection_t`]: https://nginx.org/en/docs/dev/development_guide.html#connection
+    pub fn as_ngx_connection(&self) -> *mut ngx_connection_t {
+        self as *const Connection as *mut ngx_connection_t
+    }
+
+    /// Remote (client) address.
+    ///
+    /// Returns `None` for non-IP connections (e.g. UNIX-domain sockets).
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        // SAFETY: `sockaddr` is set for all accepted connections.
+        unsafe {
+            sockaddr_to_socket_addr(self.0.sockaddr)
+        }
+    }
+
+    /// Remote (client) address in text form.
+    pub fn addr_text(&self) -> &NgxStr {
+        unsafe {
+            NgxStr::from_ngx_str(self.0.addr_text)
+        }
+    }
+
+    /// Local (server) address the connection was accepted on.
+    ///
+    /// For wildcard listening sockets, this may require a `getsockname()` system call.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        let c = self.as_ngx_connection();
+        // SAFETY: `ngx_connection_local_sockaddr` only fills in the cached `local_sockaddr`.
+        unsafe {
+            if ngx_connection_local_sockaddr(c, ptr::null_mut(), 0) != NGX_OK as ngx_int_t {
+                return None;
+            }
+            sockaddr_to_socket_addr((*c).local_sockaddr)
+        }
+    }
+
+    /// Is this an SSL/TLS connection?
+    pub fn is_ssl(&self) -> bool {
+        !self.0.ssl.is_null()
+    }
+
+    /// Number of bytes sent on this connection.
+    pub fn sent(&self) -> usize {
+        self.0.sent as usize
+    }
+
+    /// Connection log.
+    pub fn log(&self) -> *mut ngx_log_t {
+        self.0.log
+    }
+}
+
+/// Convert an IPv4 or IPv6 `sockaddr` into a [`SocketAddr`].
+pub(crate) unsafe fn sockaddr_to_socket_addr(sa: *const sockaddr) -> Option<SocketAddr> {
+    if sa.is_null() {
+        return None;
+    }
+
+    match (*sa).sa_family as i32 {
+        NGX_RS_AF_INET => {
+            let sin = sa as *const sockaddr_in;
+            let ip = Ipv4Addr::from(u32::from_be((*sin).sin_addr.s_addr));
+            let port = u16::from_be((*sin).sin_port);
+            Some(SocketAddr::V4(SocketAddrV4::new(ip, port)))
+        },
+        NGX_RS_AF_INET6 => {
+            let sin6 = sa as *const sockaddr_in6;
+            let ip = Ipv6Addr::from(ptr::read(&(*sin6).sin6_addr as *const _ as *const [u8; 16]));
+            let port = u16::from_be((*sin6).sin6_port);
+            let flowinfo = u32::from_be((*sin6).sin6_flowinfo);
+            Some(SocketAddr::V6(SocketAddrV6::new(ip, port, flowinfo, (*sin6).sin6_scope_id)))
+        },
+        _ => None,
+    }
+}
+
+/// Fill in an `ngx_sockaddr_t` from a [`SocketAddr`], returning the `sockaddr` length.
+pub(crate) fn socket_addr_to_sockaddr(addr: &SocketAddr, sa: &mut ngx_sockaddr_t) -> socklen_t {
+    // SAFETY: An all-zero `sockaddr` is valid, and each variant is only written through its own
+    // union field.
+    unsafe {
+        *sa = mem::zeroed();
+
+        match addr {
+            SocketAddr::V4(addr) => {
+                let sin = &mut sa.sockaddr_in;
+                sin.sin_family = NGX_RS_AF_INET as _;
+                sin.sin_port = addr.port().to_be();
+                sin.sin_addr.s_addr = u32::from(*addr.ip()).to_be();
+                mem::size_of::<sockaddr_in>() as socklen_t
+            },
+            SocketAddr::V6(addr) => {
+                let sin6 = &mut sa.sockaddr_in6;
+                sin6.sin6_family = NGX_RS_AF_INET6 as _;
+                sin6.sin6_port = addr.port().to_be();
+                sin6.sin6_flowinfo = addr.flowinfo().to_be();
+                sin6.sin6_scope_id = addr.scope_id();
+                ptr::write(&mut sin6.sin6_addr as *mut _ as *mut [u8; 16], addr.ip().octets());
+                mem::size_of::<sockaddr_in6>() as socklen_t
+            },
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::bindings::*;
+use crate::core::*;
+
+use std::os::raw::c_void;
+use std::ptr;
+
+/// Wrapper for an Nginx [cycle] (`ngx_cycle_t`), the runtime context created from a
+/// configuration.
+///
+/// [cycle]: https://nginx.org/en/docs/dev/development_guide.html#cycle
+#[repr(transparent)]
+pub struct Cycle(ngx_cycle_t);
+
+impl Cycle {
+    /// Create a [`Cycle`] from an [`ngx_cycle_t`].
+    ///
+    /// [`ngx_cycle_t`]: https://nginx.org/en/docs/dev/development_guide.html#cycle
+    pub unsafe fn from_ngx_cycle<'a>(cycle: *mut ngx_cycle_t) -> &'a mut Cycle {
+        // SAFETY: The caller has provided a valid non-null pointer to a valid `ngx_cycle_t`
+        // which shares the same representation as `Cycle`.
+        &mut *cycle.cast::<Cycle>()
+    }
+
+    /// The current cycle (`ngx_cycle`).
+    ///
+    /// While a configuration is being loaded, this is still the previous cycle. The returned
+    /// reference must not be kept across a reload.
+    pub unsafe fn current<'a>() -> &'a Cycle {
+        let cycle = ptr::read_volatile(ptr::addr_of!(ngx_cycle));
+        assert!(!cycle.is_null());
+        Cycle::from_ngx_cycle(cycle)
+    }
+
+    /// Pointer to the underlying [`ngx_cycle_t`].
+    ///
+    /// [`ngx_cycle_t`]: https://nginx.org/en/docs/dev/development_guide.html#cycle
+    pub fn as_ngx_cycle(&self) -> *mut ngx_cycle_t {
+        &self.0 as *const ngx_cycle_t as *mut ngx_cycle_t
+    }
+
+    /// Cycle pool, destroyed with the cycle (e.g. on configuration reload).
+    pub fn pool(&self) -> Pool {
+        // SAFETY: Every cycle has a valid pool.
+        unsafe { Pool::from_ngx_pool(self.0.pool) }
+    }
+
+    /// Cycle log.
+    pub fn log(&self) -> *mut ngx_log_t {
+        self.0.log
+    }
+
+    /// Is this the initial cycle, created before the configuration is first loaded?
+    pub fn is_init_cycle(&self) -> bool {
+        self.0.conf_ctx.is_null()
+    }
+
+    /// The cycle this cycle replaced on reload, or `None` on startup.
+    ///
+    /// This is only available while the new cycle is being initialized (e.g. in
+    /// [`ModuleLifecycle::on_init_module`] or shared zone initialization).
+    pub fn old_cycle(&self) -> Option<&Cycle> {
+        if self.0.old_cycle.is_null() {
+            return None;
+        }
+
+        // SAFETY: The old cycle is valid until the new one is initialized.
+        let old_cycle = unsafe { Cycle::from_ngx_cycle(self.0.old_cycle) };
+        if old_cycle.is_init_cycle() {
+            return None;
+        }
+
+        Some(old_cycle)
+    }
+
+    /// Installation prefix (`-p` command line option).
+    pub fn prefix(&self) -> &NgxStr {
+        unsafe { NgxStr::from_ngx_str(self.0.prefix) }
+    }
+
+    /// Configuration prefix, the directory of the configuration file.
+    pub fn conf_prefix(&self) -> &NgxStr {
+        unsafe { NgxStr::from_ngx_str(self.0.conf_prefix) }
+    }
+
+    /// Path of the configuration file.
+    pub fn conf_file(&self) -> &NgxStr {
+        unsafe { NgxStr::from_ngx_str(self.0.conf_file) }
+    }
+
+    /// Host name of the machine.
+    pub fn hostname(&self) -> &NgxStr {
+        unsafe { NgxStr::from_ngx_str(self.0.hostname) }
+    }
+
+    /// Configuration of a core module (`ngx_get_conf`), or null if the module has none.
+    ///
+    /// For HTTP modules, use [`ngx_http_cycle_get_module_main_conf`] instead.
+    ///
+    /// [`ngx_http_cycle_get_module_main_conf`]: crate::http::ngx_http_cycle_get_module_main_conf
+    pub fn get_conf(&self, module: &ngx_module_t) -> *mut c_void {
+        if self.is_init_cycle() {
+            return ptr::null_mut();
+        }
+
+        unsafe { *self.0.conf_ctx.add(module.index) as *mut c_void }
+    }
+
+    /// Number of worker processes (`worker_processes` directive).
+    pub fn worker_processes(&self) -> usize {
+        let ccf = unsafe { self.get_conf(&ngx_core_module) } as *mut ngx_core_conf_t;
+        if ccf.is_null() {
+            return 0;
+        }
+
+        unsafe { (*ccf).worker_processes as usize }
+    }
+
+    /// Shared memory zones of the cycle.
+    pub fn shared_memory(&self) -> ListIter<ngx_shm_zone_t> {
+        // SAFETY: The list of shared memory zones is initialized with the cycle.
+        unsafe { NgxList::<ngx_shm_zone_t>::from_ngx_list_ref(&self.0.shared_memory).iter() }
+    }
+}
@@ -0,0 +1,181 @@
+use crate::bindings::*;
+use crate::core::*;
+
+use std::marker::PhantomData;
+use std::{mem, ptr};
+use std::os::raw::{c_char, c_void};
+
+/// Builder for a [`HashTable`], used at configuration time.
+///
+/// Besides exact keys, wildcard keys (`*.example.com`, `.example.com` or `www.example.*`) are
+/// supported, just like with the `server_name` and `map` directives.
+///
+/// ```ignore
+/// let mut builder = HashBuilder::new(cf).ok_or(())?;
+/// builder.add("example.com", 1)?;
+/// builder.add("*.example.org", 2)?;
+/// conf.hosts = builder.build("hosts_hash", 512, 64);
+/// ```
+pub struct HashBuilder<V> {
+    cf: *mut ngx_conf_t,
+    keys: *mut ngx_hash_keys_arrays_t,
+    _marker: PhantomData<V>,
+}
+
+impl<V> HashBuilder<V> {
+    /// Create a builder allocating from the configuration pools.
+    pub unsafe fn new(cf: *mut ngx_conf_t) -> Option<HashBuilder<V>> {
+        let mut temp_pool = Pool::from_ngx_pool((*cf).temp_pool);
+        let keys = temp_pool.calloc_type::<ngx_hash_keys_arrays_t>();
+        if keys.is_null() {
+            return None;
+        }
+
+        (*keys).pool = (*cf).pool;
+        (*keys).temp_pool = (*cf).temp_pool;
+
+        if ngx_hash_keys_array_init(keys, NGX_HASH_SMALL as ngx_uint_t) != NGX_OK as ngx_int_t {
+            return None;
+        }
+
+        Some(HashBuilder { cf, keys, _marker: PhantomData })
+    }
+
+    /// Add a key (matched case-insensitively).
+    ///
+    /// Fails if the key is a duplicate, an invalid wildcard, or memory can not be allocated.
+    pub fn add(&mut self, key: &str, value: V) -> Result<(), ()> {
+        // SAFETY: The configuration pool outlives the hash table.
+        let mut pool = unsafe { Pool::from_ngx_pool((*self.cf).pool) };
+
+        let mut key = pool.create_ngx_str(key.as_bytes()).ok_or(())?;
+        let value = pool.allocate(value);
+        if value.is_null() {
+            return Err(());
+        }
+
+        let flags = (NGX_HASH_WILDCARD_KEY | NGX_HASH_READONLY_KEY) as ngx_uint_t;
+        let rc = unsafe { ngx_hash_add_key(self.keys, &mut key, value as *mut c_void, flags) };
+        if rc != NGX_OK as ngx_int_t {
+            return Err(());
+        }
+
+        Ok(())
+    }
+
+    /// Build the hash table.
+    ///
+    /// `max_size` and `bucket_size` are the usual `*_hash_max_size` and `*_hash_bucket_size`
+    /// parameters. `name` is used in error messages if they are too small.
+    pub fn build(self, name: &str, max_size: usize, bucket_size: usize) -> Option<HashTable<V>> {
+        unsafe {
+            let mut pool = Pool::from_ngx_pool((*self.cf).pool);
+
+            let hash = pool.calloc_type::<ngx_hash_combined_t>();
+            if hash.is_null() {
+                return None;
+            }
+
+            let mut c_name = Vec::with_capacity(name.len() + 1);
+            c_name.extend_from_slice(name.as_bytes());
+            c_name.push(0);
+            let c_name = pool.create_ngx_str(&c_name)?;
+
+            let mut hinit: ngx_hash_init_t = mem::zeroed();
+            hinit.key = Some(ngx_hash_key_lc);
+            hinit.max_size = max_size;
+            hinit.bucket_size = bucket_size;
+            hinit.name = c_name.data as *mut c_char;
+            hinit.pool = (*self.cf).pool;
+
+            let keys = &mut *self.keys;
+
+            let exact = NgxArray::<ngx_hash_key_t>::from_ngx_array(&mut keys.keys).as_mut_slice();
+            if !exact.is_empty() {
+                hinit.hash = &mut (*hash).hash;
+                hinit.temp_pool = ptr::null_mut();
+
+                if ngx_hash_init(&mut hinit, exact.as_mut_ptr(), exact.len()) != NGX_OK as ngx_int_t {
+                    return None;
+                }
+            }
+
+            if keys.dns_wc_head.nelts != 0 {
+                (*hash).wc_head = build_wildcard(&mut hinit, (*self.cf).temp_pool, &mut keys.dns_wc_head)?;
+            }
+
+            if keys.dns_wc_tail.nelts != 0 {
+                (*hash).wc_tail = build_wildcard(&mut hinit, (*self.cf).temp_pool, &mut keys.dns_wc_tail)?;
+            }
+
+            Some(HashTable { hash, _marker: PhantomData })
+        }
+    }
+}
+
+unsafe fn build_wildcard(hinit: &mut ngx_hash_init_t, temp_pool: *mut ngx_pool_t, keys: &mut ngx_array_t) -> Option<*mut ngx_hash_wildcard_t> {
+    let keys = NgxArray::<ngx_hash_key_t>::from_ngx_array(keys).as_mut_slice();
+    keys.sort_by(|a, b| ngx_dns_strcmp(a.key.data, b.key.data).cmp(&0));
+
+    hinit.hash = ptr::null_mut();
+    hinit.temp_pool = temp_pool;
+
+    if ngx_hash_wildcard_init(hinit, keys.as_mut_ptr(), keys.len()) != NGX_OK as ngx_int_t {
+        return None;
+    }
+
+    Some(hinit.hash as *mut ngx_hash_wildcard_t)
+}
+
+/// Static lookup table built with a [`HashBuilder`] ([`ngx_hash_combined_t`]).
+///
+/// The table is allocated from the configuration pool, so it's valid for as long as the
+/// configuration it was built for.
+///
+/// [`ngx_hash_combined_t`]: https://nginx.org/en/docs/dev/development_guide.html#hash
+pub struct HashTable<V> {
+    hash: *mut ngx_hash_combined_t,
+    _marker: PhantomData<V>,
+}
+
+impl<V> Clone for HashTable<V> {
+    fn clone(&self) -> Self {
+        HashTable { hash: self.hash, _marker: PhantomData }
+    }
+}
+
+impl<V> Copy for HashTable<V> {}
+
+/// Longest name lowercased on the stack by [`HashTable::find`].
+const MAX_STACK_NAME_LEN: usize = 256;
+
+impl<V> HashTable<V> {
+    /// Look up `name` (ignoring case), trying exact keys first, then wildcard keys.
+    ///
+    /// `name` is lowercased into a stack buffer, unless it's longer than 256 bytes. Names that
+    /// are already lowercase can be looked up with [`HashTable::find_lowercase`] instead.
+    pub fn find(&self, name: &[u8]) -> Option<&V> {
+        if name.len() > MAX_STACK_NAME_LEN {
+            let name = name.to_ascii_lowercase();
+            // SAFETY: `name` is readable for its length.
+            let key = unsafe { ngx_hash_key(name.as_ptr() as *mut u_char, name.len()) };
+            return self.find_lowercase(key, &name);
+        }
+
+        let mut buf = [0u8; MAX_STACK_NAME_LEN];
+        let lowercase = &mut buf[..name.len()];
+        // SAFETY: `lowercase` has room for the lowercased `name`.
+        let key = unsafe { ngx_hash_strlow(lowercase.as_mut_ptr(), name.as_ptr() as *mut u_char, name.len()) };
+        self.find_lowercase(key, lowercase)
+    }
+
+    /// Look up a lowercase `name` whose hash is `key` (as computed by `ngx_hash_key`), like
+    /// `ngx_hash_find_combined`.
+    pub fn find_lowercase(&self, key: ngx_uint_t, name: &[u8]) -> Option<&V> {
+        unsafe {
+            // The name is only read, despite the signature
+            let value = ngx_hash_find_combined(self.hash, key, name.as_ptr() as *mut u_char, name.len());
+            (value as *const V).as_ref()
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::bindings::*;
+
+use std::marker::PhantomData;
+use std::{mem, ptr};
+
+/// Typed view of an Nginx [list] (`ngx_list_t`) of `T`.
+///
+/// Elements are allocated from the list's pool and are never dropped.
+///
+/// [list]: https://nginx.org/en/docs/dev/development_guide.html#list
+#[repr(transparent)]
+pub struct NgxList<T>(ngx_list_t, PhantomData<T>);
+
+impl<T> NgxList<T> {
+    /// Create an [`NgxList`] from an [`ngx_list_t`] whose elements are of type `T`.
+    pub unsafe fn from_ngx_list<'a>(l: *mut ngx_list_t) -> &'a mut NgxList<T> {
+        assert!(!l.is_null());
+        debug_assert!((*l).size == 0 || (*l).size == mem::size_of::<T>());
+        // SAFETY: The caller has provided a valid pointer to an `ngx_list_t` of `T`, which
+        // shares the same representation as `NgxList<T>`.
+        &mut *l.cast::<NgxList<T>>()
+    }
+
+    /// Create a shared [`NgxList`] from an [`ngx_list_t`] whose elements are of type `T`.
+    pub unsafe fn from_ngx_list_ref<'a>(l: *const ngx_list_t) -> &'a NgxList<T> {
+        assert!(!l.is_null());
+        debug_assert!((*l).size == 0 || (*l).size == mem::size_of::<T>());
+        // SAFETY: The caller has provided a valid pointer to an `ngx_list_t` of `T`, which
+        // shares the same representation as `NgxList<T>`.
+        &*l.cast::<NgxList<T>>()
+    }
+
+    /// Pointer to the underlying [`ngx_list_t`].
+    pub fn as_ngx_list(&mut self) -> *mut ngx_list_t {
+        &mut self.0
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns `true` if the list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Iterate over the elements.
+    pub fn iter(&self) -> ListIter<T> {
+        ListIter { part: &self.0.part, index: 0, _marker: PhantomData }
+    }
+
+    /// Iterate mutably over the elements.
+    pub fn iter_mut(&mut self) -> ListIterMut<T> {
+        ListIterMut { part: &mut self.0.part, index: 0, _marker: PhantomData }
+    }
+
+    /// Append an element, allocating a new list part if needed.
+    ///
+    /// Returns `None` if memory can not be allocated.
+    pub fn push(&mut self, value: T) -> Option<&mut T> {
+        unsafe {
+            let p = ngx_list_push(&mut self.0) as *mut T;
+            if p.is_null() {
+                return None;
+            }
+            ptr::write(p, value);
+            Some(&mut *p)
+        }
+    }
+}
+
+/// Advance to the next element of a list, returning a pointer to it.
+unsafe fn next_elt<T>(part: &mut *const ngx_list_part_t, index: &mut usize) -> Option<*mut T> {
+    loop {
+        if part.is_null() {
+            return None;
+        }
+
+        if *index < (**part).nelts {
+            let elt = ((**part).elts as *mut T).add(*index);
+            *index += 1;
+            return Some(elt);
+        }
+
+        *part = (**part).next;
+        *index = 0;
+    }
+}
+
+/// Iterator over the elements of an [`NgxList`].
+pub struct ListIter<'a, T> {
+    part: *const ngx_list_part_t,
+    index: usize,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<'a, T> Iterator for ListIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: The list parts are valid and contain `nelts` initialized elements each.
+        unsafe { next_elt::<T>(&mut self.part, &mut self.index).map(|elt| &*elt) }
+    }
+}
+
+/// Mutable iterator over the elements of an [`NgxList`].
+pub struct ListIterMut<'a, T> {
+    part: *const ngx_list_part_t,
+    index: usize,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, T> Iterator for ListIterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: The list parts are valid and contain `nelts` initialized elements each.
+        unsafe { next_elt::<T>(&mut self.part, &mut self.index).map(|elt| &mut *elt) }
+    }
+}
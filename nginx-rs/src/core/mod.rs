@@ -1,12 +1,42 @@
+mod array;
 mod buffer;
+mod conf;
+mod connection;
+mod cycle;
+mod hash;
+mod list;
+mod module;
 mod pool;
+mod queue;
+mod rbtree;
+mod shm;
+mod slab;
+#[cfg(feature = "ssl")]
+mod ssl;
 mod status;
 mod string;
+#[cfg(feature = "thread_pool")]
+mod thread_pool;
 
+pub use array::*;
 pub use buffer::*;
+pub use conf::*;
+pub use connection::*;
+pub use cycle::*;
+pub use hash::*;
+pub use list::*;
+pub use module::*;
 pub use pool::*;
+pub use queue::*;
+pub use rbtree::*;
+pub use shm::*;
+pub use slab::*;
+#[cfg(feature = "ssl")]
+pub use ssl::*;
 pub use status::*;
 pub use string::*;
+#[cfg(feature = "thread_pool")]
+pub use thread_pool::*;
 
 /// Static empty configuration directive initializer for [`ngx_command_t`].
 ///
@@ -0,0 +1,75 @@
+use crate::bindings::*;
+use crate::core::*;
+
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+/// Process lifecycle hooks of a module (the `init_*` and `exit_*` fields of [`ngx_module_t`]).
+///
+/// Implement the `on_*` methods, then reference the matching `extern "C"` functions in the
+/// module definition, e.g. `init_process: Some(Module::init_process)`. The `init_master` hook
+/// is never called by Nginx, so it isn't modeled.
+///
+/// [`ngx_module_t`]: https://nginx.org/en/docs/dev/development_guide.html#modules
+pub trait ModuleLifecycle {
+    /// Called in the master process after the configuration is loaded, including on reload.
+    fn on_init_module(_cycle: &mut Cycle) -> Status {
+        OK
+    }
+
+    /// Called in each worker process after it's started, e.g. to open per-worker resources or
+    /// start timers.
+    fn on_init_process(_cycle: &mut Cycle) -> Status {
+        OK
+    }
+
+    /// Called in each worker process before it exits.
+    fn on_exit_process(_cycle: &mut Cycle) {}
+
+    /// Called in the master process before it exits.
+    fn on_exit_master(_cycle: &mut Cycle) {}
+
+    unsafe extern "C" fn init_module(cycle: *mut ngx_cycle_t) -> ngx_int_t {
+        Self::on_init_module(Cycle::from_ngx_cycle(cycle)).into()
+    }
+
+    unsafe extern "C" fn init_process(cycle: *mut ngx_cycle_t) -> ngx_int_t {
+        Self::on_init_process(Cycle::from_ngx_cycle(cycle)).into()
+    }
+
+    unsafe extern "C" fn exit_process(cycle: *mut ngx_cycle_t) {
+        Self::on_exit_process(Cycle::from_ngx_cycle(cycle));
+    }
+
+    unsafe extern "C" fn exit_master(cycle: *mut ngx_cycle_t) {
+        Self::on_exit_master(Cycle::from_ngx_cycle(cycle));
+    }
+}
+
+/// Core module (`NGX_CORE_MODULE`), configured at the main level of the configuration file.
+///
+/// The module context is an [`ngx_core_module_t`]:
+///
+/// ```ignore
+/// static ngx_telemetry_module_ctx: ngx_core_module_t = ngx_core_module_t {
+///     name: ngx_string!("telemetry"),
+///     create_conf: Some(Module::create_conf),
+///     init_conf: Some(Module::init_conf),
+/// };
+/// ```
+///
+/// Directives of type `NGX_MAIN_CONF|NGX_DIRECT_CONF` receive a pointer to [`CoreModule::Conf`].
+///
+/// [`ngx_core_module_t`]: https://nginx.org/en/docs/dev/development_guide.html#core_modules
+pub trait CoreModule {
+    type Conf: Default;
+
+    unsafe extern "C" fn create_conf(cycle: *mut ngx_cycle_t) -> *mut c_void {
+        let mut pool = Cycle::from_ngx_cycle(cycle).pool();
+        pool.allocate::<Self::Conf>(Default::default()) as *mut c_void
+    }
+
+    unsafe extern "C" fn init_conf(_cycle: *mut ngx_cycle_t, _conf: *mut c_void) -> *mut c_char {
+        ptr::null_mut()
+    }
+}
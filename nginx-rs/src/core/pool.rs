@@ -1,5 +1,7 @@
 use crate::bindings::*;
 use crate::core::buffer::{TemporaryBuffer, MemoryBuffer, Buffer};
+use crate::core::array::NgxArray;
+use crate::core::list::NgxList;
 
 use std::{ptr, mem};
 use std::os::raw::c_void;
@@ -12,6 +14,13 @@ impl Pool {
         Pool(pool)
     }
 
+    /// Pointer to the underlying [`ngx_pool_t`].
+    ///
+    /// [`ngx_pool_t`]: https://nginx.org/en/docs/dev/development_guide.html#pool
+    pub fn as_ngx_pool(&self) -> *mut ngx_pool_t {
+        self.0
+    }
+
     pub fn create_buffer(&mut self, size: usize) -> Option<TemporaryBuffer> {
         let buf = unsafe { ngx_create_temp_buf(self.0, size) };
         if buf.is_null() {
@@ -53,6 +62,48 @@ impl Pool {
         Some(MemoryBuffer::from_ngx_buf(buf))
     }
 
+    /// Copy `bytes` into an [`ngx_str_t`] allocated from the pool.
+    ///
+    /// [`ngx_str_t`]: https://nginx.org/en/docs/dev/development_guide.html#string_overview
+    pub fn create_ngx_str(&mut self, bytes: &[u8]) -> Option<ngx_str_t> {
+        let data = self.alloc(bytes.len()) as *mut u_char;
+        if data.is_null() {
+            return None;
+        }
+
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), data, bytes.len());
+        }
+
+        Some(ngx_str_t { len: bytes.len(), data })
+    }
+
+    /// Create an array of `T` with initial capacity `n`.
+    ///
+    /// Returns `None` if memory can not be allocated.
+    pub fn create_array<T>(&mut self, n: usize) -> Option<&mut NgxArray<T>> {
+        unsafe {
+            let a = ngx_array_create(self.0, n, mem::size_of::<T>());
+            if a.is_null() {
+                return None;
+            }
+            Some(NgxArray::from_ngx_array(a))
+        }
+    }
+
+    /// Create a list of `T` with `n` elements per list part.
+    ///
+    /// Returns `None` if memory can not be allocated.
+    pub fn create_list<T>(&mut self, n: usize) -> Option<&mut NgxList<T>> {
+        unsafe {
+            let l = ngx_list_create(self.0, n, mem::size_of::<T>());
+            if l.is_null() {
+                return None;
+            }
+            Some(NgxList::from_ngx_list(l))
+        }
+    }
+
     unsafe fn add_cleanup_for_value<T>(&mut self, value: *mut T) -> Result<(), ()> {
         let cln = ngx_pool_cleanup_add(self.0, 0);
         if cln.is_null() {
@@ -0,0 +1,119 @@
+use crate::bindings::*;
+
+use std::marker::PhantomData;
+use std::ptr;
+
+/// Intrusive doubly-linked [queue] (`ngx_queue_t`).
+///
+/// A [`Queue`] is the sentinel of the queue, to which `ngx_queue_t` fields of other
+/// structures are linked. It must not be moved once elements have been inserted, so it's
+/// usually allocated from a pool or a shared memory zone. A zeroed (or default) queue is empty.
+///
+/// [queue]: https://nginx.org/en/docs/dev/development_guide.html#queue
+#[repr(transparent)]
+pub struct Queue(ngx_queue_t);
+
+impl Default for Queue {
+    fn default() -> Self {
+        Queue(ngx_queue_t { prev: ptr::null_mut(), next: ptr::null_mut() })
+    }
+}
+
+impl Queue {
+    /// Create a [`Queue`] from an [`ngx_queue_t`] sentinel.
+    pub unsafe fn from_ngx_queue<'a>(q: *mut ngx_queue_t) -> &'a mut Queue {
+        // SAFETY: The caller has provided a valid non-null pointer to an `ngx_queue_t` sentinel
+        // which shares the same representation as `Queue`.
+        &mut *q.cast::<Queue>()
+    }
+
+    /// Pointer to the underlying [`ngx_queue_t`] sentinel.
+    pub fn as_ngx_queue(&mut self) -> *mut ngx_queue_t {
+        self.ensure_init();
+        &mut self.0
+    }
+
+    /// Initialize the sentinel on first use (the queue can't be initialized before it's placed).
+    fn ensure_init(&mut self) {
+        if self.0.next.is_null() {
+            let q = &mut self.0 as *mut ngx_queue_t;
+            self.0.prev = q;
+            self.0.next = q;
+        }
+    }
+
+    /// Returns `true` if the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.next.is_null() || ptr::eq(self.0.next, &self.0)
+    }
+
+    /// Insert `x` at the head of the queue.
+    pub unsafe fn insert_head(&mut self, x: *mut ngx_queue_t) {
+        self.ensure_init();
+        (*x).next = self.0.next;
+        (*(*x).next).prev = x;
+        (*x).prev = &mut self.0;
+        self.0.next = x;
+    }
+
+    /// Insert `x` at the tail of the queue.
+    pub unsafe fn insert_tail(&mut self, x: *mut ngx_queue_t) {
+        self.ensure_init();
+        (*x).prev = self.0.prev;
+        (*(*x).prev).next = x;
+        (*x).next = &mut self.0;
+        self.0.prev = x;
+    }
+
+    /// Remove `x` from the queue it's linked into.
+    pub unsafe fn remove(x: *mut ngx_queue_t) {
+        (*(*x).next).prev = (*x).prev;
+        (*(*x).prev).next = (*x).next;
+        (*x).prev = ptr::null_mut();
+        (*x).next = ptr::null_mut();
+    }
+
+    /// First element of the queue.
+    pub fn head(&self) -> Option<*mut ngx_queue_t> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.0.next)
+    }
+
+    /// Last element of the queue.
+    pub fn last(&self) -> Option<*mut ngx_queue_t> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.0.prev)
+    }
+
+    /// Iterate over the elements of the queue, from head to tail.
+    pub fn iter(&self) -> QueueIter {
+        let next = if self.is_empty() { ptr::null_mut() } else { self.0.next };
+        QueueIter { sentinel: &self.0, next, _marker: PhantomData }
+    }
+}
+
+/// Iterator over the elements of a [`Queue`].
+pub struct QueueIter<'a> {
+    sentinel: *const ngx_queue_t,
+    next: *mut ngx_queue_t,
+    _marker: PhantomData<&'a Queue>,
+}
+
+impl<'a> Iterator for QueueIter<'a> {
+    type Item = *mut ngx_queue_t;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() || ptr::eq(self.next, self.sentinel) {
+            return None;
+        }
+
+        let q = self.next;
+        // SAFETY: Elements of the queue are linked into a circular list through the sentinel.
+        self.next = unsafe { (*q).next };
+        Some(q)
+    }
+}
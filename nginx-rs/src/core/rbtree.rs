@@ -0,0 +1,289 @@
+use crate::bindings::*;
+use crate::core::Queue;
+
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use std::ptr;
+
+/// An entry of an [`RbTree`].
+///
+/// Entries are ordered by the `key` of their tree node (usually a hash of the entry key),
+/// then by [`RbTreeEntry::key`].
+///
+/// # Safety
+///
+/// The implementing type must be `#[repr(C)]` with an [`ngx_rbtree_node_t`] as its first field.
+pub unsafe trait RbTreeEntry {
+    type Key: ?Sized + Ord;
+
+    /// Entry key.
+    fn key(&self) -> &Self::Key;
+}
+
+/// Intrusive [red-black tree] (`ngx_rbtree_t`).
+///
+/// The tree must not be moved once entries have been inserted, so it's usually allocated from
+/// a pool or a shared memory zone. A default tree is empty.
+///
+/// [red-black tree]: https://nginx.org/en/docs/dev/development_guide.html#red_black_tree
+#[repr(C)]
+pub struct RbTree<T: RbTreeEntry> {
+    tree: ngx_rbtree_t,
+    sentinel: ngx_rbtree_node_t,
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T: RbTreeEntry> Default for RbTree<T> {
+    fn default() -> Self {
+        RbTree {
+            tree: ngx_rbtree_t { root: ptr::null_mut(), sentinel: ptr::null_mut(), insert: None },
+            sentinel: ngx_rbtree_node_t {
+                key: 0,
+                left: ptr::null_mut(),
+                right: ptr::null_mut(),
+                parent: ptr::null_mut(),
+                color: 0,
+                data: 0,
+            },
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: RbTreeEntry> RbTree<T> {
+    /// Pointer to the underlying [`ngx_rbtree_t`].
+    pub fn as_ngx_rbtree(&mut self) -> *mut ngx_rbtree_t {
+        self.ensure_init();
+        &mut self.tree
+    }
+
+    /// Initialize the tree on first use (the tree can't be initialized before it's placed).
+    fn ensure_init(&mut self) {
+        if self.tree.sentinel.is_null() {
+            // ngx_rbtree_init(): the sentinel is black
+            self.sentinel.color = 0;
+            self.tree.root = &mut self.sentinel;
+            self.tree.sentinel = &mut self.sentinel;
+            self.tree.insert = Some(insert_value::<T>);
+        }
+    }
+
+    /// Returns `true` if the tree is empty.
+    pub fn is_empty(&self) -> bool {
+        self.tree.sentinel.is_null() || self.tree.root == self.tree.sentinel
+    }
+
+    /// Insert an entry whose node `key` has been set.
+    ///
+    /// The entry must remain valid (and not move) until it's deleted from the tree.
+    pub unsafe fn insert(&mut self, entry: *mut T) {
+        self.ensure_init();
+        // The module may be loaded at a different address after a reload, while a tree
+        // in shared memory is reused
+        self.tree.insert = Some(insert_value::<T>);
+        ngx_rbtree_insert(&mut self.tree, entry as *mut ngx_rbtree_node_t);
+    }
+
+    /// Delete an entry from the tree.
+    pub unsafe fn delete(&mut self, entry: *mut T) {
+        ngx_rbtree_delete(&mut self.tree, entry as *mut ngx_rbtree_node_t);
+    }
+
+    fn find(&self, hash: ngx_rbtree_key_t, key: &T::Key) -> Option<*mut T> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let sentinel = self.tree.sentinel;
+        let mut node = self.tree.root;
+
+        // SAFETY: The tree only contains valid entries of type `T`.
+        unsafe {
+            while node != sentinel {
+                if hash != (*node).key {
+                    node = if hash < (*node).key { (*node).left } else { (*node).right };
+                    continue;
+                }
+
+                match key.cmp((*(node as *mut T)).key()) {
+                    Ordering::Less => node = (*node).left,
+                    Ordering::Greater => node = (*node).right,
+                    Ordering::Equal => return Some(node as *mut T),
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Look up the entry with node key `hash` and entry key `key`.
+    pub fn get(&self, hash: ngx_rbtree_key_t, key: &T::Key) -> Option<&T> {
+        self.find(hash, key).map(|entry| unsafe { &*entry })
+    }
+
+    /// Look up the entry with node key `hash` and entry key `key`.
+    pub fn get_mut(&mut self, hash: ngx_rbtree_key_t, key: &T::Key) -> Option<&mut T> {
+        self.find(hash, key).map(|entry| unsafe { &mut *entry })
+    }
+
+    /// Iterate over the entries in order.
+    pub fn iter(&self) -> RbTreeIter<T> {
+        let mut node = ptr::null_mut();
+
+        if !self.is_empty() {
+            // ngx_rbtree_min()
+            node = self.tree.root;
+            unsafe {
+                while (*node).left != self.tree.sentinel {
+                    node = (*node).left;
+                }
+            }
+        }
+
+        RbTreeIter { tree: &self.tree as *const _ as *mut _, node, _marker: PhantomData }
+    }
+}
+
+/// Ordered iterator over the entries of an [`RbTree`].
+pub struct RbTreeIter<'a, T> {
+    tree: *mut ngx_rbtree_t,
+    node: *mut ngx_rbtree_node_t,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<'a, T: 'a> Iterator for RbTreeIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.node.is_null() {
+            return None;
+        }
+
+        // SAFETY: `node` is a valid entry of the tree. `ngx_rbtree_next` only reads the tree.
+        unsafe {
+            let entry = &*(self.node as *const T);
+            self.node = ngx_rbtree_next(self.tree, self.node);
+            Some(entry)
+        }
+    }
+}
+
+/// Tree insert function ordering by node key, then by entry key.
+unsafe extern "C" fn insert_value<T: RbTreeEntry>(mut temp: *mut ngx_rbtree_node_t, node: *mut ngx_rbtree_node_t, sentinel: *mut ngx_rbtree_node_t) {
+    let mut p: *mut *mut ngx_rbtree_node_t;
+
+    loop {
+        let left = if (*node).key != (*temp).key {
+            (*node).key < (*temp).key
+        } else {
+            (*(node as *mut T)).key() < (*(temp as *mut T)).key()
+        };
+
+        p = if left { &mut (*temp).left } else { &mut (*temp).right };
+
+        if *p == sentinel {
+            break;
+        }
+
+        temp = *p;
+    }
+
+    *p = node;
+    (*node).parent = temp;
+    (*node).left = sentinel;
+    (*node).right = sentinel;
+    // ngx_rbt_red()
+    (*node).color = 1;
+}
+
+/// An entry of an [`Lru`] cache.
+///
+/// # Safety
+///
+/// `QUEUE_OFFSET` must be the offset of an `ngx_queue_t` field of the entry
+/// (e.g. `std::mem::offset_of!(Node, queue)`).
+pub unsafe trait LruEntry: RbTreeEntry {
+    const QUEUE_OFFSET: usize;
+}
+
+/// Least recently used cache index, built from an [`RbTree`] and a [`Queue`].
+///
+/// This is the usual structure of Nginx shared memory caches (e.g. `limit_req`). The cache
+/// doesn't own its entries: they are allocated by the caller (from a pool or a slab pool) and
+/// must be freed once evicted.
+#[repr(C)]
+pub struct Lru<T: LruEntry> {
+    tree: RbTree<T>,
+    queue: Queue,
+    len: usize,
+}
+
+impl<T: LruEntry> Default for Lru<T> {
+    fn default() -> Self {
+        Lru { tree: RbTree::default(), queue: Queue::default(), len: 0 }
+    }
+}
+
+impl<T: LruEntry> Lru<T> {
+    fn queue_of(entry: *mut T) -> *mut ngx_queue_t {
+        unsafe { (entry as *mut u8).add(T::QUEUE_OFFSET) as *mut ngx_queue_t }
+    }
+
+    fn entry_of(q: *mut ngx_queue_t) -> *mut T {
+        unsafe { (q as *mut u8).sub(T::QUEUE_OFFSET) as *mut T }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Insert an entry as the most recently used.
+    ///
+    /// The node `key` of the entry must have been set. The entry must remain valid (and not move)
+    /// until it's removed.
+    pub unsafe fn insert(&mut self, entry: *mut T) {
+        self.tree.insert(entry);
+        self.queue.insert_head(Self::queue_of(entry));
+        self.len += 1;
+    }
+
+    /// Look up an entry, marking it as the most recently used.
+    pub fn get(&mut self, hash: ngx_rbtree_key_t, key: &T::Key) -> Option<&mut T> {
+        let entry = self.tree.find(hash, key)?;
+        unsafe {
+            Queue::remove(Self::queue_of(entry));
+            self.queue.insert_head(Self::queue_of(entry));
+            Some(&mut *entry)
+        }
+    }
+
+    /// Look up an entry, without changing its position.
+    pub fn peek(&self, hash: ngx_rbtree_key_t, key: &T::Key) -> Option<&T> {
+        self.tree.get(hash, key)
+    }
+
+    /// Remove an entry.
+    pub unsafe fn remove(&mut self, entry: *mut T) {
+        Queue::remove(Self::queue_of(entry));
+        self.tree.delete(entry);
+        self.len -= 1;
+    }
+
+    /// Remove the least recently used entry, returning it so it can be freed.
+    pub fn pop_lru(&mut self) -> Option<*mut T> {
+        let entry = Self::entry_of(self.queue.last()?);
+        unsafe { self.remove(entry) };
+        Some(entry)
+    }
+
+    /// Iterate over the entries, in key order.
+    pub fn iter(&self) -> RbTreeIter<T> {
+        self.tree.iter()
+    }
+}
@@ -0,0 +1,188 @@
+use crate::bindings::*;
+use crate::core::*;
+
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+/// Types which can be stored in shared memory.
+///
+/// Values in shared memory are accessed by all worker processes and are never dropped, so they
+/// must not contain pointers to process memory (e.g. a `String` or `Box`) or need [`Drop`]. This
+/// is implemented for all [`Copy`] types.
+///
+/// # Safety
+///
+/// Implementors must only contain plain data and other [`ShmData`] types.
+pub unsafe trait ShmData {}
+
+unsafe impl<T: Copy> ShmData for T {}
+
+/// A [shared memory] zone holding a value of type `T`.
+///
+/// The value is allocated from the zone's slab pool when the zone is first created and is reused
+/// across configuration reloads, as long as the zone name and size are unchanged. Use [`ShmVec`]
+/// and [`ShmHashMap`] for dynamically sized data.
+///
+/// [shared memory]: https://nginx.org/en/docs/dev/development_guide.html#shared_memory
+pub struct SharedZone<T: ShmData> {
+    zone: *mut ngx_shm_zone_t,
+    _marker: PhantomData<T>,
+}
+
+impl<T: ShmData> Clone for SharedZone<T> {
+    fn clone(&self) -> Self {
+        SharedZone { zone: self.zone, _marker: PhantomData }
+    }
+}
+
+impl<T: ShmData> Copy for SharedZone<T> {}
+
+impl<T: ShmData + Default> SharedZone<T> {
+    /// Add a shared memory zone called `name` of `size` bytes.
+    ///
+    /// `tag` is usually the module adding the zone. Adding a zone with the same name and tag
+    /// returns the same zone (e.g. if it's referenced by several directives).
+    pub unsafe fn add(cf: *mut ngx_conf_t, name: &NgxStr, size: usize, tag: &ngx_module_t) -> Option<SharedZone<T>> {
+        let mut name = ngx_str_t { len: name.as_bytes().len(), data: name.as_bytes().as_ptr() as *mut u_char };
+        let tag = tag as *const ngx_module_t as *mut c_void;
+
+        let zone = ngx_shared_memory_add(cf, &mut name, size, tag);
+        if zone.is_null() {
+            return None;
+        }
+
+        (*zone).init = Some(init_zone::<T>);
+
+        Some(SharedZone { zone, _marker: PhantomData })
+    }
+
+    /// Handle a zone directive of the form `directive name size;`.
+    ///
+    /// The zone is stored in `zone`. Returns `NGX_CONF_OK` (null) on success.
+    pub unsafe fn add_from_directive(cf: *mut ngx_conf_t, tag: &ngx_module_t, zone: &mut Option<SharedZone<T>>) -> *mut c_char {
+        if zone.is_some() {
+            return "is duplicate\0".as_ptr() as *mut c_char;
+        }
+
+        let args = NgxArray::<ngx_str_t>::from_ngx_array((*cf).args).as_slice();
+        let name = NgxStr::from_ngx_str(args[1]);
+        let size = ngx_parse_size(&args[2] as *const ngx_str_t as *mut ngx_str_t);
+
+        if name.is_empty() {
+            return "invalid zone name\0".as_ptr() as *mut c_char;
+        }
+
+        // Leave room for the slab pool bookkeeping
+        if size == NGX_ERROR as ssize_t || (size as usize) < 8 * ngx_pagesize {
+            return "invalid zone size\0".as_ptr() as *mut c_char;
+        }
+
+        *zone = SharedZone::add(cf, name, size as usize, tag);
+        if zone.is_none() {
+            return NGX_CONF_ERROR;
+        }
+
+        ptr::null_mut()
+    }
+}
+
+impl<T: ShmData> SharedZone<T> {
+    /// Zone name.
+    pub fn name(&self) -> &NgxStr {
+        unsafe { NgxStr::from_ngx_str((*self.zone).shm.name) }
+    }
+
+    /// Slab pool at the start of the zone.
+    fn shpool(&self) -> *mut ngx_slab_pool_t {
+        unsafe { (*self.zone).shm.addr as *mut ngx_slab_pool_t }
+    }
+
+    /// Lock the zone, giving exclusive access to the shared value.
+    ///
+    /// The zone must have been initialized (i.e. this can't be called during configuration).
+    pub fn lock(&self) -> SharedZoneGuard<T> {
+        let shpool = self.shpool();
+        // SAFETY: The zone was initialized with a slab pool and the shared value.
+        unsafe {
+            assert!(!(*self.zone).data.is_null());
+            ngx_shmtx_lock(&mut (*shpool).mutex);
+            let pool = SlabPool::from_locked_ngx_slab_pool(shpool);
+            SharedZoneGuard { pool, data: (*self.zone).data as *mut T, _marker: PhantomData }
+        }
+    }
+}
+
+/// Exclusive access to the value of a [`SharedZone`], unlocked on drop.
+pub struct SharedZoneGuard<'a, T: ShmData> {
+    pool: SlabPool,
+    data: *mut T,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<T: ShmData> SharedZoneGuard<'_, T> {
+    /// The zone's slab pool (already locked by this guard).
+    ///
+    /// This is the only pool [`ShmVec`] and [`ShmHashMap`] values of the zone can be used with.
+    pub fn pool(&mut self) -> &mut SlabPool {
+        &mut self.pool
+    }
+
+    /// Split the guard into the shared value and the zone's slab pool.
+    ///
+    /// This allows updating [`ShmVec`] or [`ShmHashMap`] fields of the shared value.
+    pub fn split(&mut self) -> (&mut T, &mut SlabPool) {
+        unsafe { (&mut *self.data, &mut self.pool) }
+    }
+}
+
+impl<T: ShmData> Deref for SharedZoneGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.data }
+    }
+}
+
+impl<T: ShmData> DerefMut for SharedZoneGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.data }
+    }
+}
+
+impl<T: ShmData> Drop for SharedZoneGuard<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            ngx_shmtx_unlock(&mut (*self.pool.as_ngx_slab_pool()).mutex);
+        }
+    }
+}
+
+unsafe extern "C" fn init_zone<T: ShmData + Default>(zone: *mut ngx_shm_zone_t, data: *mut c_void) -> ngx_int_t {
+    // Reuse the value of the old cycle's zone on reload
+    if !data.is_null() {
+        (*zone).data = data;
+        return OK.into();
+    }
+
+    let shpool = (*zone).shm.addr as *mut ngx_slab_pool_t;
+
+    if (*zone).shm.exists != 0 {
+        (*zone).data = (*shpool).data;
+        return OK.into();
+    }
+
+    let value = ngx_slab_alloc(shpool, mem::size_of::<T>()) as *mut T;
+    if value.is_null() {
+        return ERROR.into();
+    }
+
+    ptr::write(value, T::default());
+
+    (*shpool).data = value as *mut c_void;
+    (*zone).data = value as *mut c_void;
+
+    OK.into()
+}
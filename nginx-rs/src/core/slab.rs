@@ -0,0 +1,457 @@
+use crate::bindings::*;
+use crate::core::ShmData;
+
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+use std::{mem, ptr, slice};
+
+/// Wrapper for the [slab allocator] of a shared memory zone.
+///
+/// A [`SlabPool`] is either unlocked (allocations take the pool mutex) or locked (obtained from
+/// a [`SharedZoneGuard`](crate::core::SharedZoneGuard), which already holds the mutex). Only a
+/// locked pool can be used with [`ShmVec`] and [`ShmHashMap`].
+///
+/// [slab allocator]: https://nginx.org/en/docs/dev/development_guide.html#shared_memory
+pub struct SlabPool {
+    pool: *mut ngx_slab_pool_t,
+    locked: bool,
+}
+
+impl SlabPool {
+    /// Create an unlocked [`SlabPool`] from an [`ngx_slab_pool_t`].
+    pub unsafe fn from_ngx_slab_pool(pool: *mut ngx_slab_pool_t) -> SlabPool {
+        assert!(!pool.is_null());
+        SlabPool { pool, locked: false }
+    }
+
+    /// Create a [`SlabPool`] from an [`ngx_slab_pool_t`] whose mutex is held by the caller.
+    pub unsafe fn from_locked_ngx_slab_pool(pool: *mut ngx_slab_pool_t) -> SlabPool {
+        assert!(!pool.is_null());
+        SlabPool { pool, locked: true }
+    }
+
+    /// Pointer to the underlying [`ngx_slab_pool_t`].
+    pub fn as_ngx_slab_pool(&self) -> *mut ngx_slab_pool_t {
+        self.pool
+    }
+
+    /// Allocate `size` bytes.
+    ///
+    /// Returns null if the zone is full.
+    pub fn alloc(&mut self, size: usize) -> *mut c_void {
+        unsafe {
+            if self.locked {
+                ngx_slab_alloc_locked(self.pool, size)
+            } else {
+                ngx_slab_alloc(self.pool, size)
+            }
+        }
+    }
+
+    /// Allocate `size` zeroed bytes.
+    ///
+    /// Returns null if the zone is full.
+    pub fn calloc(&mut self, size: usize) -> *mut c_void {
+        unsafe {
+            if self.locked {
+                ngx_slab_calloc_locked(self.pool, size)
+            } else {
+                ngx_slab_calloc(self.pool, size)
+            }
+        }
+    }
+
+    /// Free memory allocated from this pool.
+    pub unsafe fn free(&mut self, p: *mut c_void) {
+        if self.locked {
+            ngx_slab_free_locked(self.pool, p);
+        } else {
+            ngx_slab_free(self.pool, p);
+        }
+    }
+
+    /// Allocate and initialize a value of type `T`.
+    ///
+    /// The value is never dropped, so `T` should be plain data without pointers into process
+    /// memory. Returns null if the zone is full.
+    pub fn allocate<T: Copy>(&mut self, value: T) -> *mut T {
+        let p = self.alloc(mem::size_of::<T>()) as *mut T;
+        if !p.is_null() {
+            unsafe { ptr::write(p, value) };
+        }
+        p
+    }
+
+    /// Offset of `p` from the start of the pool.
+    fn offset_of<T>(&self, p: *const T) -> usize {
+        p as usize - self.pool as usize
+    }
+
+    /// Pointer at `offset` from the start of the pool.
+    fn ptr_at<T>(&self, offset: usize) -> *mut T {
+        unsafe { (self.pool as *mut u8).add(offset) as *mut T }
+    }
+}
+
+/// A growable array in shared memory.
+///
+/// Elements are stored at an offset from the start of the zone, so a [`ShmVec`] can itself be
+/// stored in shared memory. All methods must be passed the locked pool of the zone the vector
+/// lives in (see [`SharedZoneGuard::pool`](crate::core::SharedZoneGuard::pool)): the vector is
+/// bound to the first pool it allocates from, and panics if it's used with any other pool or with
+/// an unlocked one.
+#[repr(C)]
+pub struct ShmVec<T: Copy> {
+    pool: usize,
+    offset: usize,
+    len: usize,
+    capacity: usize,
+    _marker: PhantomData<T>,
+}
+
+// SAFETY: The elements are `Copy` and referenced by an offset within the zone.
+unsafe impl<T: Copy> ShmData for ShmVec<T> {}
+
+impl<T: Copy> Default for ShmVec<T> {
+    fn default() -> Self {
+        ShmVec::new()
+    }
+}
+
+impl<T: Copy> ShmVec<T> {
+    /// Create an empty vector.
+    pub const fn new() -> ShmVec<T> {
+        ShmVec { pool: 0, offset: 0, len: 0, capacity: 0, _marker: PhantomData }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the vector is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of elements the vector can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Check that `pool` is locked and is the pool the vector was allocated from.
+    fn check(&self, pool: &SlabPool) {
+        assert!(pool.locked, "shared memory accessed without holding the zone lock");
+        assert!(
+            self.pool == 0 || self.pool == pool.pool as usize,
+            "shared memory accessed with the slab pool of another zone"
+        );
+    }
+
+    /// The elements as a slice.
+    pub fn as_slice<'a>(&'a self, pool: &'a SlabPool) -> &'a [T] {
+        self.check(pool);
+        if self.capacity == 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(pool.ptr_at(self.offset), self.len) }
+    }
+
+    /// The elements as a mutable slice.
+    pub fn as_mut_slice<'a>(&'a mut self, pool: &'a mut SlabPool) -> &'a mut [T] {
+        self.check(pool);
+        if self.capacity == 0 {
+            return &mut [];
+        }
+        unsafe { slice::from_raw_parts_mut(pool.ptr_at(self.offset), self.len) }
+    }
+
+    /// Element at `index`.
+    pub fn get<'a>(&'a self, pool: &'a SlabPool, index: usize) -> Option<&'a T> {
+        self.as_slice(pool).get(index)
+    }
+
+    /// Resize the vector to hold `capacity` elements.
+    fn reallocate(&mut self, pool: &mut SlabPool, capacity: usize) -> Result<(), ()> {
+        self.check(pool);
+
+        let size = capacity.checked_mul(mem::size_of::<T>()).ok_or(())?;
+        let p = pool.alloc(size) as *mut T;
+        if p.is_null() {
+            return Err(());
+        }
+
+        if self.capacity != 0 {
+            let old = pool.ptr_at::<T>(self.offset);
+            unsafe {
+                ptr::copy_nonoverlapping(old, p, self.len);
+                pool.free(old as *mut c_void);
+            }
+        }
+
+        self.pool = pool.pool as usize;
+        self.offset = pool.offset_of(p);
+        self.capacity = capacity;
+
+        Ok(())
+    }
+
+    /// Append an element, growing the vector if needed.
+    ///
+    /// Fails if the zone is full.
+    pub fn push(&mut self, pool: &mut SlabPool, value: T) -> Result<(), ()> {
+        self.check(pool);
+        if self.len == self.capacity {
+            self.reallocate(pool, usize::max(4, self.capacity * 2))?;
+        }
+
+        unsafe { ptr::write(pool.ptr_at::<T>(self.offset).add(self.len), value) };
+        self.len += 1;
+
+        Ok(())
+    }
+
+    /// Remove the last element.
+    pub fn pop(&mut self, pool: &mut SlabPool) -> Option<T> {
+        self.check(pool);
+        if self.len == 0 {
+            return None;
+        }
+
+        self.len -= 1;
+        Some(unsafe { ptr::read(pool.ptr_at::<T>(self.offset).add(self.len)) })
+    }
+
+    /// Remove the element at `index`, replacing it with the last element.
+    pub fn swap_remove(&mut self, pool: &mut SlabPool, index: usize) -> T {
+        let len = self.len;
+        let slice = self.as_mut_slice(pool);
+        slice.swap(index, len - 1);
+        self.pop(pool).unwrap()
+    }
+
+    /// Remove all elements and free the memory used by the vector.
+    pub fn clear(&mut self, pool: &mut SlabPool) {
+        self.check(pool);
+        if self.capacity != 0 {
+            unsafe { pool.free(pool.ptr_at::<c_void>(self.offset)) };
+        }
+        *self = ShmVec::new();
+    }
+}
+
+#[repr(C, u8)]
+#[derive(Copy, Clone)]
+enum Slot<K: Copy, V: Copy> {
+    Empty,
+    Deleted,
+    Full(K, V),
+}
+
+/// 64-bit [FNV-1a] hasher, whose output doesn't depend on the Rust version (unlike
+/// `DefaultHasher`).
+///
+/// [FNV-1a]: http://www.isthe.com/chongo/tech/comp/fnv/
+struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        FnvHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// A hash map in shared memory.
+///
+/// This uses open addressing, with entries stored in a [`ShmVec`], so it must be used with the
+/// locked pool of its zone too. Keys are hashed with FNV-1a, and the map and its entries have a
+/// `#[repr(C)]` layout, so it stays readable across reloads with a module built by another Rust
+/// version, as long as `K` and `V` are `#[repr(C)]` (or primitive) types and the key's [`Hash`]
+/// implementation doesn't change.
+#[repr(C)]
+pub struct ShmHashMap<K: Copy + Eq + Hash, V: Copy> {
+    slots: ShmVec<Slot<K, V>>,
+    len: usize,
+    used: usize,
+}
+
+// SAFETY: The entries are `Copy` and stored in a `ShmVec`.
+unsafe impl<K: Copy + Eq + Hash, V: Copy> ShmData for ShmHashMap<K, V> {}
+
+impl<K: Copy + Eq + Hash, V: Copy> Default for ShmHashMap<K, V> {
+    fn default() -> Self {
+        ShmHashMap::new()
+    }
+}
+
+impl<K: Copy + Eq + Hash, V: Copy> ShmHashMap<K, V> {
+    /// Create an empty map.
+    pub const fn new() -> ShmHashMap<K, V> {
+        ShmHashMap { slots: ShmVec::new(), len: 0, used: 0 }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn hash(key: &K) -> usize {
+        let mut hasher = FnvHasher::default();
+        key.hash(&mut hasher);
+        hasher.finish() as usize
+    }
+
+    /// Index of the slot holding `key`.
+    fn find(&self, pool: &SlabPool, key: &K) -> Option<usize> {
+        let slots = self.slots.as_slice(pool);
+        if slots.is_empty() {
+            return None;
+        }
+
+        let mask = slots.len() - 1;
+        let mut i = Self::hash(key) & mask;
+        loop {
+            match slots[i] {
+                Slot::Empty => return None,
+                Slot::Full(k, _) if k == *key => return Some(i),
+                _ => i = (i + 1) & mask,
+            }
+        }
+    }
+
+    /// Value for `key`.
+    pub fn get<'a>(&'a self, pool: &'a SlabPool, key: &K) -> Option<&'a V> {
+        let i = self.find(pool, key)?;
+        match &self.slots.as_slice(pool)[i] {
+            Slot::Full(_, v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Mutable value for `key`.
+    pub fn get_mut<'a>(&'a mut self, pool: &'a mut SlabPool, key: &K) -> Option<&'a mut V> {
+        let i = self.find(pool, key)?;
+        match &mut self.slots.as_mut_slice(pool)[i] {
+            Slot::Full(_, v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the map contains `key`.
+    pub fn contains_key(&self, pool: &SlabPool, key: &K) -> bool {
+        self.find(pool, key).is_some()
+    }
+
+    /// Rebuild the table with `capacity` slots (a power of two).
+    fn rehash(&mut self, pool: &mut SlabPool, capacity: usize) -> Result<(), ()> {
+        let mut slots = ShmVec::new();
+        slots.reallocate(pool, capacity)?;
+        for _ in 0..capacity {
+            slots.push(pool, Slot::Empty)?;
+        }
+
+        let mut old = mem::replace(&mut self.slots, slots);
+        self.used = 0;
+        self.len = 0;
+
+        for i in 0..old.len() {
+            if let Slot::Full(k, v) = old.as_slice(pool)[i] {
+                self.insert_slot(pool, k, v);
+            }
+        }
+
+        old.clear(pool);
+
+        Ok(())
+    }
+
+    /// Insert into a table with a free slot, returning the previous value.
+    fn insert_slot(&mut self, pool: &mut SlabPool, key: K, value: V) -> Option<V> {
+        if let Some(i) = self.find(pool, &key) {
+            let slot = &mut self.slots.as_mut_slice(pool)[i];
+            let old = mem::replace(slot, Slot::Full(key, value));
+            return match old {
+                Slot::Full(_, v) => Some(v),
+                _ => None,
+            };
+        }
+
+        let slots = self.slots.as_mut_slice(pool);
+        let mask = slots.len() - 1;
+        let mut i = Self::hash(&key) & mask;
+        loop {
+            match slots[i] {
+                Slot::Full(..) => i = (i + 1) & mask,
+                Slot::Empty => {
+                    self.used += 1;
+                    break;
+                },
+                Slot::Deleted => break,
+            }
+        }
+
+        slots[i] = Slot::Full(key, value);
+        self.len += 1;
+
+        None
+    }
+
+    /// Insert a value, returning the previous value for `key`.
+    ///
+    /// Fails if the zone is full.
+    pub fn insert(&mut self, pool: &mut SlabPool, key: K, value: V) -> Result<Option<V>, ()> {
+        // Keep the load factor (including deleted slots) below 3/4
+        let capacity = self.slots.len();
+        if (self.used + 1) * 4 > capacity * 3 {
+            let capacity = if (self.len + 1) * 2 > capacity { usize::max(8, capacity * 2) } else { capacity };
+            self.rehash(pool, capacity)?;
+        }
+
+        Ok(self.insert_slot(pool, key, value))
+    }
+
+    /// Remove `key`, returning its value.
+    pub fn remove(&mut self, pool: &mut SlabPool, key: &K) -> Option<V> {
+        let i = self.find(pool, key)?;
+        let slot = &mut self.slots.as_mut_slice(pool)[i];
+        match mem::replace(slot, Slot::Deleted) {
+            Slot::Full(_, v) => {
+                self.len -= 1;
+                Some(v)
+            },
+            _ => None,
+        }
+    }
+
+    /// Iterate over the entries, in arbitrary order.
+    pub fn iter<'a>(&'a self, pool: &'a SlabPool) -> impl Iterator<Item = (&'a K, &'a V)> {
+        self.slots.as_slice(pool).iter().filter_map(|slot| match slot {
+            Slot::Full(k, v) => Some((k, v)),
+            _ => None,
+        })
+    }
+
+    /// Remove all entries and free the memory used by the map.
+    pub fn clear(&mut self, pool: &mut SlabPool) {
+        self.slots.clear(pool);
+        self.len = 0;
+        self.used = 0;
+    }
+}
@@ -0,0 +1,112 @@
+use crate::bindings::*;
+use crate::core::*;
+use crate::ngx_null_string;
+
+type SslGetter = unsafe extern "C" fn(*mut ngx_connection_t, *mut ngx_pool_t, *mut ngx_str_t) -> ngx_int_t;
+
+/// SSL/TLS state of a [`Connection`].
+///
+/// Values are obtained through the same `ngx_ssl_*` getters that back the `$ssl_*` variables,
+/// so modules don't need to link OpenSSL themselves. Strings are allocated from the given pool
+/// (usually the request pool), and can't outlive its borrow.
+pub struct SslConnection<'a>(&'a Connection);
+
+impl Connection {
+    /// SSL/TLS state, if this is an SSL/TLS connection.
+    pub fn ssl(&self) -> Option<SslConnection> {
+        if self.is_ssl() {
+            Some(SslConnection(self))
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> SslConnection<'a> {
+    fn get<'p>(&self, pool: &'p mut Pool, getter: SslGetter) -> Option<&'p NgxStr> {
+        let mut s = ngx_null_string!();
+        // SAFETY: This is an SSL connection and the getter stores a string allocated from `pool`
+        // (or a static string) in `s` if it successfully returns.
+        unsafe {
+            if getter(self.0.as_ngx_connection(), pool.as_ngx_pool(), &mut s) != NGX_OK as ngx_int_t || s.len == 0 {
+                return None;
+            }
+            Some(NgxStr::from_ngx_str(s))
+        }
+    }
+
+    /// Server name requested by the client through [SNI].
+    ///
+    /// [SNI]: https://en.wikipedia.org/wiki/Server_Name_Indication
+    pub fn server_name<'p>(&self, pool: &'p mut Pool) -> Option<&'p NgxStr> {
+        self.get(pool, ngx_ssl_get_server_name)
+    }
+
+    /// Negotiated protocol (e.g. `TLSv1.3`).
+    pub fn protocol<'p>(&self, pool: &'p mut Pool) -> Option<&'p NgxStr> {
+        self.get(pool, ngx_ssl_get_protocol)
+    }
+
+    /// Negotiated cipher (e.g. `TLS_AES_256_GCM_SHA384`).
+    pub fn cipher<'p>(&self, pool: &'p mut Pool) -> Option<&'p NgxStr> {
+        self.get(pool, ngx_ssl_get_cipher_name)
+    }
+
+    /// Client certificate verification result (`SUCCESS`, `FAILED:reason` or `NONE`).
+    pub fn client_verify<'p>(&self, pool: &'p mut Pool) -> Option<&'p NgxStr> {
+        self.get(pool, ngx_ssl_get_client_verify)
+    }
+
+    /// Client certificate in PEM format.
+    pub fn client_certificate_pem<'p>(&self, pool: &'p mut Pool) -> Option<&'p NgxStr> {
+        self.get(pool, ngx_ssl_get_raw_certificate)
+    }
+
+    /// Client certificate in DER format.
+    pub fn client_certificate_der<'p>(&self, pool: &'p mut Pool) -> Option<&'p NgxStr> {
+        let pem = self.client_certificate_pem(pool)?;
+
+        let mut base64: Vec<u8> = pem.as_bytes()
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.starts_with(b"-----"))
+            .flatten()
+            .copied()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+
+        let data = pool.alloc((base64.len() + 3) / 4 * 3) as *mut u_char;
+        if data.is_null() {
+            return None;
+        }
+
+        let mut src = ngx_str_t { len: base64.len(), data: base64.as_mut_ptr() };
+        let mut dst = ngx_str_t { len: 0, data };
+        // SAFETY: `dst` has room for the decoded length of `src`.
+        unsafe {
+            if ngx_decode_base64(&mut dst, &mut src) != NGX_OK as ngx_int_t {
+                return None;
+            }
+            Some(NgxStr::from_ngx_str(dst))
+        }
+    }
+
+    /// Client certificate subject DN (RFC 2253 format).
+    pub fn client_subject_dn<'p>(&self, pool: &'p mut Pool) -> Option<&'p NgxStr> {
+        self.get(pool, ngx_ssl_get_subject_dn)
+    }
+
+    /// Client certificate issuer DN (RFC 2253 format).
+    pub fn client_issuer_dn<'p>(&self, pool: &'p mut Pool) -> Option<&'p NgxStr> {
+        self.get(pool, ngx_ssl_get_issuer_dn)
+    }
+
+    /// Client certificate serial number.
+    pub fn client_serial<'p>(&self, pool: &'p mut Pool) -> Option<&'p NgxStr> {
+        self.get(pool, ngx_ssl_get_serial_number)
+    }
+
+    /// Client certificate SHA1 fingerprint.
+    pub fn client_fingerprint<'p>(&self, pool: &'p mut Pool) -> Option<&'p NgxStr> {
+        self.get(pool, ngx_ssl_get_fingerprint)
+    }
+}
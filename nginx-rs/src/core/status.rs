@@ -18,3 +18,6 @@ impl Into<ngx_int_t> for Status {
 pub const OK: Status = Status(NGX_OK as ngx_int_t);
 pub const ERROR: Status = Status(NGX_ERROR as ngx_int_t);
 pub const AGAIN: Status = Status(NGX_AGAIN as ngx_int_t);
+pub const BUSY: Status = Status(NGX_BUSY as ngx_int_t);
+pub const DONE: Status = Status(NGX_DONE as ngx_int_t);
+pub const DECLINED: Status = Status(NGX_DECLINED as ngx_int_t);
@@ -0,0 +1,109 @@
+use crate::bindings::*;
+use crate::core::*;
+
+use std::mem;
+use std::os::raw::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// A [thread pool] (`ngx_thread_pool_t`), for running blocking work off the event loop.
+///
+/// Thread pools are defined with the `thread_pool` directive, except for the `default` pool.
+///
+/// [thread pool]: https://nginx.org/en/docs/dev/development_guide.html#threads
+#[derive(Clone, Copy)]
+pub struct ThreadPool(*mut ngx_thread_pool_t);
+
+impl ThreadPool {
+    /// Reference the thread pool called `name` during configuration.
+    ///
+    /// Configuration fails later if the pool isn't defined with the `thread_pool` directive.
+    pub unsafe fn add(cf: *mut ngx_conf_t, name: &NgxStr) -> Option<ThreadPool> {
+        let mut name = ngx_str_t { len: name.as_bytes().len(), data: name.as_bytes().as_ptr() as *mut u_char };
+        let tp = ngx_thread_pool_add(cf, &mut name);
+        if tp.is_null() {
+            return None;
+        }
+
+        Some(ThreadPool(tp))
+    }
+
+    /// Look up the thread pool called `name`, once the configuration has been loaded.
+    pub unsafe fn get(cycle: *mut ngx_cycle_t, name: &NgxStr) -> Option<ThreadPool> {
+        let mut name = ngx_str_t { len: name.as_bytes().len(), data: name.as_bytes().as_ptr() as *mut u_char };
+        let tp = ngx_thread_pool_get(cycle, &mut name);
+        if tp.is_null() {
+            return None;
+        }
+
+        Some(ThreadPool(tp))
+    }
+
+    /// Pointer to the underlying [`ngx_thread_pool_t`].
+    ///
+    /// [`ngx_thread_pool_t`]: https://nginx.org/en/docs/dev/development_guide.html#threads
+    pub fn as_ngx_thread_pool(&self) -> *mut ngx_thread_pool_t {
+        self.0
+    }
+
+    /// Run `work` on a thread of the pool, then `complete` with its result on the event loop.
+    ///
+    /// The result is `None` if `work` panicked. The task is allocated from `pool`, which must
+    /// outlive it (e.g. by holding a reference to the request owning the pool).
+    pub fn post<T, F, C>(&self, pool: &mut Pool, log: *mut ngx_log_t, work: F, complete: C) -> Result<(), ()>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+        C: FnOnce(Option<T>) + 'static,
+    {
+        // `ngx_thread_task_alloc` places the context right after the `ngx_thread_task_t`
+        assert!(mem::align_of::<ThreadTask<T, F, C>>() <= mem::align_of::<ngx_thread_task_t>());
+
+        unsafe {
+            let task = ngx_thread_task_alloc(pool.as_ngx_pool(), mem::size_of::<ThreadTask<T, F, C>>());
+            if task.is_null() {
+                return Err(());
+            }
+
+            let ctx = (*task).ctx as *mut ThreadTask<T, F, C>;
+            ptr::write(ctx, ThreadTask { work: Some(work), result: None, complete: Some(complete) });
+
+            (*task).handler = Some(thread_handler::<T, F, C>);
+            (*task).event.data = ctx as *mut c_void;
+            (*task).event.handler = Some(completion_handler::<T, F, C>);
+            (*task).event.log = log;
+
+            if ngx_thread_task_post(self.0, task) != NGX_OK as ngx_int_t {
+                ptr::drop_in_place(ctx);
+                return Err(());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct ThreadTask<T, F, C> {
+    // Only accessed by the pool's thread while the task is running
+    work: Option<F>,
+    result: Option<T>,
+    // Only accessed on the event loop
+    complete: Option<C>,
+}
+
+/// Run the work on the pool's thread.
+unsafe extern "C" fn thread_handler<T, F: FnOnce() -> T, C>(data: *mut c_void, _log: *mut ngx_log_t) {
+    let task = data as *mut ThreadTask<T, F, C>;
+    if let Some(work) = (*task).work.take() {
+        // Panics must not unwind into the thread pool
+        (*task).result = panic::catch_unwind(AssertUnwindSafe(work)).ok();
+    }
+}
+
+/// Deliver the result on the event loop, once the work is done.
+unsafe extern "C" fn completion_handler<T, F, C: FnOnce(Option<T>)>(ev: *mut ngx_event_t) {
+    let task = ptr::read((*ev).data as *mut ThreadTask<T, F, C>);
+    if let Some(complete) = task.complete {
+        complete(task.result);
+    }
+}
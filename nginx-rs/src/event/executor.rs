@@ -0,0 +1,216 @@
+use crate::bindings::*;
+use crate::event::*;
+
+use std::cell::{Cell, RefCell, UnsafeCell};
+use std::future::Future;
+use std::mem;
+use std::os::raw::c_void;
+use std::pin::Pin;
+use std::ptr;
+use std::rc::Rc;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::time::Duration;
+
+struct Task {
+    event: UnsafeCell<ngx_event_t>,
+    future: RefCell<Option<Pin<Box<dyn Future<Output = ()>>>>>,
+    canceled: Cell<bool>,
+    // Set while a detached task is running, to keep it alive
+    detached: RefCell<Option<Rc<Task>>>,
+}
+
+impl Task {
+    fn is_finished(&self) -> bool {
+        self.canceled.get() || self.future.try_borrow().map_or(false, |f| f.is_none())
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        let ev = self.event.get();
+        unsafe {
+            if (*ev).posted() != 0 {
+                delete_posted_event(ev);
+            }
+        }
+    }
+}
+
+/// Spawn a future on the worker's event loop.
+///
+/// The executor is single-threaded: the future is polled from a posted event at the end of the
+/// current event loop iteration, then each time it's woken. Its [`Waker`] posts the task's event,
+/// so it must only be used from the worker's thread.
+///
+/// The future is dropped (canceling it) when the returned handle is dropped, unless the handle is
+/// [detached](TaskHandle::detach).
+pub fn spawn<F: Future<Output = ()> + 'static>(future: F) -> TaskHandle {
+    let task = Rc::new(Task {
+        // SAFETY: An all-zero `ngx_event_t` is an idle event.
+        event: UnsafeCell::new(unsafe { mem::zeroed() }),
+        future: RefCell::new(Some(Box::pin(future))),
+        canceled: Cell::new(false),
+        detached: RefCell::new(None),
+    });
+
+    unsafe {
+        let ev = task.event.get();
+        (*ev).data = Rc::as_ptr(&task) as *mut c_void;
+        (*ev).handler = Some(task_handler);
+        (*ev).log = (*ngx_cycle).log;
+        post_event(ev, posted_events());
+    }
+
+    TaskHandle { task }
+}
+
+/// Handle to a task created with [`spawn`], canceling it on drop.
+pub struct TaskHandle {
+    task: Rc<Task>,
+}
+
+impl TaskHandle {
+    /// Returns `true` if the task has completed or was canceled.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Log used when polling the task, the cycle log by default.
+    pub fn set_log(&mut self, log: *mut ngx_log_t) {
+        unsafe { (*self.task.event.get()).log = log };
+    }
+
+    /// Cancel the task, dropping its future.
+    ///
+    /// A task canceled while it's being polled is dropped once the poll returns.
+    pub fn cancel(&mut self) {
+        let task = &self.task;
+        task.canceled.set(true);
+        task.detached.borrow_mut().take();
+
+        let ev = task.event.get();
+        unsafe {
+            if (*ev).posted() != 0 {
+                delete_posted_event(ev);
+            }
+        }
+
+        let future = task.future.try_borrow_mut().ok().and_then(|mut f| f.take());
+        drop(future);
+    }
+
+    /// Let the task run to completion without keeping a handle to it.
+    pub fn detach(self) {
+        let this = mem::ManuallyDrop::new(self);
+        // SAFETY: The handle is never used or dropped again.
+        let task = unsafe { ptr::read(&this.task) };
+        if !task.is_finished() {
+            *task.detached.borrow_mut() = Some(task.clone());
+        }
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+unsafe extern "C" fn task_handler(ev: *mut ngx_event_t) {
+    // Hold a reference while polling, the future may drop the task's handle
+    let task = (*ev).data as *const Task;
+    Rc::increment_strong_count(task);
+    let task = Rc::from_raw(task);
+
+    // Take the future while polling, so that canceling the task from within doesn't drop it
+    let future = task.future.borrow_mut().take();
+    let mut future = match future {
+        Some(future) => future,
+        None => return,
+    };
+
+    let waker = waker(&task);
+    let mut cx = Context::from_waker(&waker);
+
+    match future.as_mut().poll(&mut cx) {
+        Poll::Pending if !task.canceled.get() => {
+            *task.future.borrow_mut() = Some(future);
+        }
+        _ => {
+            drop(future);
+            task.detached.borrow_mut().take();
+        }
+    }
+}
+
+fn waker(task: &Rc<Task>) -> Waker {
+    let raw = RawWaker::new(Rc::into_raw(task.clone()) as *const (), &WAKER_VTABLE);
+    // SAFETY: The vtable functions uphold the `RawWaker` contract, though the waker must not be
+    // used from another thread.
+    unsafe { Waker::from_raw(raw) }
+}
+
+static WAKER_VTABLE: RawWakerVTable = RawWakerVTable::new(waker_clone, waker_wake, waker_wake_by_ref, waker_drop);
+
+unsafe fn waker_clone(data: *const ()) -> RawWaker {
+    Rc::increment_strong_count(data as *const Task);
+    RawWaker::new(data, &WAKER_VTABLE)
+}
+
+unsafe fn waker_wake(data: *const ()) {
+    waker_wake_by_ref(data);
+    waker_drop(data);
+}
+
+unsafe fn waker_wake_by_ref(data: *const ()) {
+    let task = &*(data as *const Task);
+    if !task.canceled.get() {
+        post_event(task.event.get(), posted_events());
+    }
+}
+
+unsafe fn waker_drop(data: *const ()) {
+    drop(Rc::from_raw(data as *const Task));
+}
+
+/// Wait for `duration`, using a [`Timer`].
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep { duration, timer: None, state: Rc::new(SleepState { fired: Cell::new(false), waker: RefCell::new(None) }) }
+}
+
+struct SleepState {
+    fired: Cell<bool>,
+    waker: RefCell<Option<Waker>>,
+}
+
+/// Future returned by [`sleep`].
+pub struct Sleep {
+    duration: Duration,
+    timer: Option<Timer>,
+    state: Rc<SleepState>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.state.fired.get() {
+            return Poll::Ready(());
+        }
+
+        *self.state.waker.borrow_mut() = Some(cx.waker().clone());
+
+        if self.timer.is_none() {
+            let state = self.state.clone();
+            let duration = self.duration;
+            self.timer = Some(Timer::after(duration, move || {
+                state.fired.set(true);
+                if let Some(waker) = state.waker.borrow_mut().take() {
+                    waker.wake();
+                }
+            }));
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,13 @@
+#[cfg(feature = "async")]
+mod executor;
+mod posted;
+#[cfg(feature = "async")]
+mod tcp;
+mod timer;
+
+#[cfg(feature = "async")]
+pub use executor::*;
+pub use posted::*;
+#[cfg(feature = "async")]
+pub use tcp::*;
+pub use timer::*;
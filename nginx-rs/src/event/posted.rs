@@ -0,0 +1,28 @@
+use crate::bindings::*;
+use crate::core::Queue;
+
+use std::ptr;
+
+/// Post an event to `queue` (`ngx_post_event`).
+///
+/// Events posted to [`ngx_posted_events`] are handled at the end of the current event loop
+/// iteration. Posting an event which is already posted does nothing.
+///
+/// [`ngx_posted_events`]: https://nginx.org/en/docs/dev/development_guide.html#posted_events
+pub unsafe fn post_event(ev: *mut ngx_event_t, queue: *mut ngx_queue_t) {
+    if (*ev).posted() == 0 {
+        (*ev).set_posted(1);
+        Queue::from_ngx_queue(queue).insert_tail(&mut (*ev).queue);
+    }
+}
+
+/// Remove a posted event from its queue (`ngx_delete_posted_event`).
+pub unsafe fn delete_posted_event(ev: *mut ngx_event_t) {
+    (*ev).set_posted(0);
+    Queue::remove(&mut (*ev).queue);
+}
+
+/// The queue of events handled at the end of the current event loop iteration.
+pub fn posted_events() -> *mut ngx_queue_t {
+    unsafe { ptr::addr_of_mut!(ngx_posted_events) }
+}
@@ -0,0 +1,424 @@
+use crate::bindings::*;
+use crate::core::*;
+use crate::event::*;
+
+use std::cell::{Cell, RefCell};
+use std::future::poll_fn;
+use std::io;
+use std::mem::{self, ManuallyDrop};
+use std::net::{SocketAddr, TcpStream};
+use std::os::raw::c_void;
+use std::os::unix::io::FromRawFd;
+use std::ptr;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// Default maximum number of idle connections kept by each worker process.
+const KEEPALIVE_CONNECTIONS: usize = 32;
+
+thread_local! {
+    static KEEPALIVE: RefCell<Vec<Box<Peer>>> = RefCell::new(Vec::new());
+    static KEEPALIVE_LIMIT: Cell<usize> = Cell::new(KEEPALIVE_CONNECTIONS);
+}
+
+// The connection refers to the peer address, so the peer is kept while the connection is idle
+struct Peer {
+    connection: *mut ngx_connection_t,
+    addr: SocketAddr,
+    sockaddr: ngx_sockaddr_t,
+    socklen: socklen_t,
+    name: String,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+    read_timeout: Option<ngx_msec_t>,
+    write_timeout: Option<ngx_msec_t>,
+}
+
+/// Non-blocking outbound TCP connection, driven by the Nginx event loop.
+///
+/// Connections are established with [`ngx_event_connect_peer`], and reads and writes wait for the
+/// connection's read and write events. This requires the `async` executor, e.g. in a handler
+/// defined with [`http_async_request_handler`]:
+///
+/// ```ignore
+/// let addr = "127.0.0.1:9000".parse().unwrap();
+/// let mut stream = AsyncTcpStream::connect(addr, Duration::from_secs(1)).await?;
+/// stream.set_read_timeout(Some(Duration::from_secs(5)));
+/// stream.write_all(b"PING\r\n").await?;
+/// let n = stream.read(&mut buf).await?;
+/// stream.keepalive(Duration::from_secs(60));
+/// ```
+///
+/// [`ngx_event_connect_peer`]: https://nginx.org/en/docs/dev/development_guide.html#connection
+/// [`http_async_request_handler`]: crate::http_async_request_handler
+pub struct AsyncTcpStream {
+    peer: Box<Peer>,
+}
+
+impl AsyncTcpStream {
+    /// Connect to `addr`, failing if the connection isn't established within `timeout`.
+    ///
+    /// An idle connection to `addr` kept with [`AsyncTcpStream::keepalive`] is reused if there
+    /// is one.
+    pub async fn connect(addr: SocketAddr, timeout: Duration) -> io::Result<AsyncTcpStream> {
+        if let Some(peer) = take_keepalive(&addr) {
+            return Ok(AsyncTcpStream::from_peer(peer));
+        }
+
+        let mut peer = Box::new(Peer {
+            connection: ptr::null_mut(),
+            addr,
+            // SAFETY: An all-zero `ngx_sockaddr_t` is valid, it's filled in below.
+            sockaddr: unsafe { mem::zeroed() },
+            socklen: 0,
+            name: addr.to_string(),
+            read_waker: None,
+            write_waker: None,
+            read_timeout: None,
+            write_timeout: None,
+        });
+        peer.socklen = socket_addr_to_sockaddr(&addr, &mut peer.sockaddr);
+
+        // SAFETY: An all-zero `ngx_peer_connection_t` has no peer, no local address and no tries.
+        let mut pc: ngx_peer_connection_t = unsafe { mem::zeroed() };
+        let mut pc_name = ngx_str_t { len: peer.name.len(), data: peer.name.as_ptr() as *mut u_char };
+        pc.sockaddr = &mut peer.sockaddr as *mut ngx_sockaddr_t as *mut sockaddr;
+        pc.socklen = peer.socklen;
+        pc.name = &mut pc_name;
+        pc.get = Some(ngx_event_get_peer);
+        pc.tries = 1;
+        unsafe {
+            pc.log = (*ngx_cycle).log;
+            pc.set_log_error(ngx_connection_log_error_e_NGX_ERROR_ERR);
+        }
+
+        let rc = unsafe { ngx_event_connect_peer(&mut pc) };
+        if rc == NGX_ERROR as ngx_int_t || rc == NGX_BUSY as ngx_int_t || rc == NGX_DECLINED as ngx_int_t {
+            return Err(io::Error::new(io::ErrorKind::ConnectionRefused, format!("connect() to {} failed", peer.name)));
+        }
+
+        peer.connection = pc.connection;
+        let mut stream = AsyncTcpStream::from_peer(peer);
+
+        if rc == NGX_AGAIN as ngx_int_t {
+            unsafe { add_timer((*pc.connection).write, duration_to_msec(timeout)) };
+            poll_fn(|cx| stream.poll_connect(cx)).await?;
+        }
+
+        Ok(stream)
+    }
+
+    fn from_peer(mut peer: Box<Peer>) -> AsyncTcpStream {
+        let c = peer.connection;
+        unsafe {
+            (*c).data = &mut *peer as *mut Peer as *mut c_void;
+            (*(*c).read).handler = Some(read_handler);
+            (*(*c).write).handler = Some(write_handler);
+            (*c).set_idle(0);
+        }
+
+        AsyncTcpStream { peer }
+    }
+
+    fn poll_connect(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let c = self.peer.connection;
+        unsafe {
+            let wev = (*c).write;
+
+            if (*wev).timedout() != 0 {
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, "connect() timed out")));
+            }
+
+            if (*wev).ready() == 0 {
+                self.peer.write_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            if (*wev).timer_set() != 0 {
+                del_timer(wev);
+            }
+
+            // Check the result of the non-blocking connect()
+            let socket = ManuallyDrop::new(TcpStream::from_raw_fd((*c).fd));
+            match socket.take_error()? {
+                Some(err) => Poll::Ready(Err(err)),
+                None => Poll::Ready(Ok(())),
+            }
+        }
+    }
+
+    /// Remote address of the connection.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer.addr
+    }
+
+    /// Pointer to the underlying [`ngx_connection_t`].
+    ///
+    /// [`ngx_connection_t`]: https://nginx.org/en/docs/dev/development_guide.html#connection
+    pub fn as_ngx_connection(&self) -> *mut ngx_connection_t {
+        self.peer.connection
+    }
+
+    /// Maximum time to wait for data to read, or `None` to wait indefinitely.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.peer.read_timeout = timeout.map(duration_to_msec);
+    }
+
+    /// Maximum time to wait for the connection to be writable, or `None` to wait indefinitely.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.peer.write_timeout = timeout.map(duration_to_msec);
+    }
+
+    /// Attempt to read into `buf`, registering the current task to be woken when the connection
+    /// is readable.
+    ///
+    /// A result of `0` bytes read means the connection was closed by the peer.
+    pub fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let c = self.peer.connection;
+        unsafe {
+            let rev = (*c).read;
+
+            if (*rev).timedout() != 0 {
+                (*rev).set_timedout(0);
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, "read timed out")));
+            }
+
+            let recv = (*c).recv.expect("connection has a recv handler");
+            let n = recv(c, buf.as_mut_ptr(), buf.len());
+
+            if n == NGX_AGAIN as ssize_t {
+                if ngx_handle_read_event(rev, 0) != NGX_OK as ngx_int_t {
+                    return Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, "failed to add read event")));
+                }
+
+                if let (Some(timeout), 0) = (self.peer.read_timeout, (*rev).timer_set()) {
+                    add_timer(rev, timeout);
+                }
+
+                self.peer.read_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            if (*rev).timer_set() != 0 {
+                del_timer(rev);
+            }
+
+            if n == NGX_ERROR as ssize_t {
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, "recv() failed")));
+            }
+
+            Poll::Ready(Ok(n as usize))
+        }
+    }
+
+    /// Attempt to write from `buf`, registering the current task to be woken when the connection
+    /// is writable.
+    pub fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let c = self.peer.connection;
+        unsafe {
+            let wev = (*c).write;
+
+            if (*wev).timedout() != 0 {
+                (*wev).set_timedout(0);
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, "write timed out")));
+            }
+
+            let send = (*c).send.expect("connection has a send handler");
+            let n = send(c, buf.as_ptr() as *mut u_char, buf.len());
+
+            if n == NGX_AGAIN as ssize_t {
+                if ngx_handle_write_event(wev, 0) != NGX_OK as ngx_int_t {
+                    return Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, "failed to add write event")));
+                }
+
+                if let (Some(timeout), 0) = (self.peer.write_timeout, (*wev).timer_set()) {
+                    add_timer(wev, timeout);
+                }
+
+                self.peer.write_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            if (*wev).timer_set() != 0 {
+                del_timer(wev);
+            }
+
+            if n == NGX_ERROR as ssize_t {
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, "send() failed")));
+            }
+
+            Poll::Ready(Ok(n as usize))
+        }
+    }
+
+    /// Read into `buf`, returning the number of bytes read (`0` at the end of the stream).
+    pub async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_read(cx, buf)).await
+    }
+
+    /// Write from `buf`, returning the number of bytes written.
+    pub async fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_write(cx, buf)).await
+    }
+
+    /// Write all of `buf`.
+    pub async fn write_all(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            let n = self.write(buf).await?;
+            if n == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            buf = &buf[n..];
+        }
+
+        Ok(())
+    }
+
+    /// Close the connection.
+    pub fn close(self) {
+        drop(self);
+    }
+
+    /// Keep the idle connection for reuse by a later [`AsyncTcpStream::connect`] to the same
+    /// address, for up to `timeout`.
+    ///
+    /// The connection is closed instead if unread data is pending. Each worker keeps a limited
+    /// number of idle connections (see [`AsyncTcpStream::set_keepalive_limit`]), closing the
+    /// oldest ones first.
+    pub fn keepalive(self, timeout: Duration) {
+        let stream = ManuallyDrop::new(self);
+        let c = stream.peer.connection;
+        // SAFETY: The stream is never used or dropped again.
+        let mut peer = unsafe { ptr::read(&stream.peer) };
+
+        // Don't keep the task that used the connection alive
+        peer.read_waker = None;
+        peer.write_waker = None;
+
+        unsafe {
+            let rev = (*c).read;
+            let wev = (*c).write;
+
+            if (*rev).ready() != 0 || (*rev).timedout() != 0 || (*wev).timedout() != 0 {
+                ngx_close_connection(c);
+                return;
+            }
+
+            if (*wev).timer_set() != 0 {
+                del_timer(wev);
+            }
+
+            if ngx_handle_read_event(rev, 0) != NGX_OK as ngx_int_t {
+                ngx_close_connection(c);
+                return;
+            }
+
+            add_timer(rev, duration_to_msec(timeout));
+
+            (*c).data = ptr::null_mut();
+            (*c).log = (*ngx_cycle).log;
+            (*rev).log = (*ngx_cycle).log;
+            (*wev).log = (*ngx_cycle).log;
+            (*rev).handler = Some(keepalive_close_handler);
+            (*wev).handler = Some(keepalive_dummy_handler);
+            (*c).set_idle(1);
+        }
+
+        KEEPALIVE.with(|cache| cache.borrow_mut().push(peer));
+        evict_keepalive();
+    }
+
+    /// Maximum number of idle connections kept by the worker process, 32 by default.
+    ///
+    /// This is usually set from `init_process`. Idle connections beyond the new limit are
+    /// closed, and a limit of `0` disables keepalive.
+    pub fn set_keepalive_limit(connections: usize) {
+        KEEPALIVE_LIMIT.with(|limit| limit.set(connections));
+        evict_keepalive();
+    }
+}
+
+/// Close the oldest idle connections beyond the keepalive limit.
+fn evict_keepalive() {
+    let limit = KEEPALIVE_LIMIT.with(Cell::get);
+    let evicted: Vec<Box<Peer>> = KEEPALIVE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let n = cache.len().saturating_sub(limit);
+        cache.drain(..n).collect()
+    });
+
+    for peer in evicted {
+        unsafe { ngx_close_connection(peer.connection) };
+    }
+}
+
+impl Drop for AsyncTcpStream {
+    fn drop(&mut self) {
+        // Also removes the connection's timers and posted events
+        unsafe { ngx_close_connection(self.peer.connection) };
+    }
+}
+
+fn take_keepalive(addr: &SocketAddr) -> Option<Box<Peer>> {
+    let peer = KEEPALIVE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let index = cache.iter().rposition(|peer| peer.addr == *addr)?;
+        Some(cache.remove(index))
+    })?;
+
+    let c = peer.connection;
+    unsafe {
+        let rev = (*c).read;
+        if (*rev).timer_set() != 0 {
+            del_timer(rev);
+        }
+
+        (*c).log = (*ngx_cycle).log;
+    }
+
+    Some(peer)
+}
+
+fn remove_keepalive(c: *mut ngx_connection_t) -> Option<Box<Peer>> {
+    KEEPALIVE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let index = cache.iter().position(|peer| peer.connection == c)?;
+        Some(cache.remove(index))
+    })
+}
+
+unsafe extern "C" fn read_handler(ev: *mut ngx_event_t) {
+    let c = (*ev).data as *mut ngx_connection_t;
+    let peer = (*c).data as *mut Peer;
+    if let Some(waker) = (*peer).read_waker.take() {
+        waker.wake();
+    }
+}
+
+unsafe extern "C" fn write_handler(ev: *mut ngx_event_t) {
+    let c = (*ev).data as *mut ngx_connection_t;
+    let peer = (*c).data as *mut Peer;
+    if let Some(waker) = (*peer).write_waker.take() {
+        waker.wake();
+    }
+}
+
+/// Close an idle connection when it times out, on any data or error from the peer, or on
+/// graceful shutdown (when `ngx_close_idle_connections` sets `c->close`).
+unsafe extern "C" fn keepalive_close_handler(ev: *mut ngx_event_t) {
+    let c = (*ev).data as *mut ngx_connection_t;
+
+    if (*ev).timedout() == 0 && (*c).close() == 0 {
+        let mut buf = [0u8; 1];
+        let recv = (*c).recv.expect("connection has a recv handler");
+        if recv(c, buf.as_mut_ptr(), 1) == NGX_AGAIN as ssize_t && ngx_handle_read_event(ev, 0) == NGX_OK as ngx_int_t {
+            return;
+        }
+    }
+
+    let peer = remove_keepalive(c);
+    ngx_close_connection(c);
+    drop(peer);
+}
+
+unsafe extern "C" fn keepalive_dummy_handler(_ev: *mut ngx_event_t) {}
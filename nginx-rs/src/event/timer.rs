@@ -0,0 +1,190 @@
+use crate::bindings::*;
+use crate::event::*;
+
+use std::cell::{Cell, RefCell, UnsafeCell};
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Add a [timer] for an event, expiring after `timer` milliseconds (`ngx_add_timer`).
+///
+/// If the timer is already set, it's only updated if the new expiry differs by more than
+/// `NGX_TIMER_LAZY_DELAY` milliseconds.
+///
+/// [timer]: https://nginx.org/en/docs/dev/development_guide.html#timers
+pub unsafe fn add_timer(ev: *mut ngx_event_t, timer: ngx_msec_t) {
+    let key = current_msec().wrapping_add(timer);
+
+    if (*ev).timer_set() != 0 {
+        let diff = key.wrapping_sub((*ev).timer.key) as ngx_msec_int_t;
+        if diff.abs() < NGX_TIMER_LAZY_DELAY as ngx_msec_int_t {
+            return;
+        }
+
+        del_timer(ev);
+    }
+
+    (*ev).timer.key = key;
+    ngx_rbtree_insert(ptr::addr_of_mut!(ngx_event_timer_rbtree), &mut (*ev).timer);
+    (*ev).set_timer_set(1);
+}
+
+/// Delete the timer of an event (`ngx_del_timer`).
+pub unsafe fn del_timer(ev: *mut ngx_event_t) {
+    ngx_rbtree_delete(ptr::addr_of_mut!(ngx_event_timer_rbtree), &mut (*ev).timer);
+    (*ev).timer.left = ptr::null_mut();
+    (*ev).timer.right = ptr::null_mut();
+    (*ev).timer.parent = ptr::null_mut();
+    (*ev).set_timer_set(0);
+}
+
+/// Cached time of the current event loop iteration, in milliseconds.
+pub fn current_msec() -> ngx_msec_t {
+    // SAFETY: `ngx_current_msec` is only updated by the worker's own thread.
+    unsafe { ptr::read_volatile(ptr::addr_of!(ngx_current_msec)) }
+}
+
+pub(crate) fn duration_to_msec(duration: Duration) -> ngx_msec_t {
+    duration.as_millis().min(ngx_msec_t::MAX as u128) as ngx_msec_t
+}
+
+struct TimerState {
+    event: UnsafeCell<ngx_event_t>,
+    callback: RefCell<Box<dyn FnMut()>>,
+    interval: Cell<Option<ngx_msec_t>>,
+}
+
+/// A callback run on the Nginx event loop, after a delay or on the next loop iteration.
+///
+/// The timer owns its [`ngx_event_t`]. Dropping the timer cancels it, so a timer allocated from
+/// a request pool (with [`Pool::allocate`]) is canceled when the request is freed:
+///
+/// ```ignore
+/// let timer = Timer::after(Duration::from_secs(1), move || unsafe {
+///     ngx_http_finalize_request(r, send_delayed_response(r));
+/// });
+/// request.pool().allocate(timer);
+/// ```
+///
+/// [`ngx_event_t`]: https://nginx.org/en/docs/dev/development_guide.html#events
+/// [`Pool::allocate`]: crate::core::Pool::allocate
+pub struct Timer {
+    state: Rc<TimerState>,
+}
+
+impl Timer {
+    /// Create a timer running `callback`, which isn't scheduled yet.
+    pub fn new<F: FnMut() + 'static>(callback: F) -> Timer {
+        let state = Rc::new(TimerState {
+            // SAFETY: An all-zero `ngx_event_t` is an idle event.
+            event: UnsafeCell::new(unsafe { mem::zeroed() }),
+            callback: RefCell::new(Box::new(callback)),
+            interval: Cell::new(None),
+        });
+
+        unsafe {
+            let ev = state.event.get();
+            (*ev).data = Rc::as_ptr(&state) as *mut c_void;
+            (*ev).handler = Some(timer_handler);
+            (*ev).log = (*ngx_cycle).log;
+        }
+
+        Timer { state }
+    }
+
+    /// Create a timer running `callback` once, after `delay`.
+    pub fn after<F: FnMut() + 'static>(delay: Duration, callback: F) -> Timer {
+        let mut timer = Timer::new(callback);
+        timer.schedule(delay);
+        timer
+    }
+
+    /// Create a timer running `callback` every `period`, until it's canceled.
+    ///
+    /// Interval timers are cancelable, i.e. they don't delay a graceful shutdown of the worker.
+    pub fn interval<F: FnMut() + 'static>(period: Duration, callback: F) -> Timer {
+        let mut timer = Timer::new(callback);
+        timer.state.interval.set(Some(duration_to_msec(period)));
+        timer.set_cancelable(true);
+        timer.schedule(period);
+        timer
+    }
+
+    /// Pointer to the underlying [`ngx_event_t`].
+    ///
+    /// [`ngx_event_t`]: https://nginx.org/en/docs/dev/development_guide.html#events
+    pub fn as_ngx_event(&self) -> *mut ngx_event_t {
+        self.state.event.get()
+    }
+
+    /// Log used by the timer's event, the cycle log by default.
+    pub fn set_log(&mut self, log: *mut ngx_log_t) {
+        unsafe { (*self.as_ngx_event()).log = log };
+    }
+
+    /// Whether a pending timer shouldn't delay a graceful shutdown of the worker.
+    pub fn set_cancelable(&mut self, cancelable: bool) {
+        unsafe { (*self.as_ngx_event()).set_cancelable(cancelable as _) };
+    }
+
+    /// Run the callback after `delay`, replacing any pending expiry.
+    pub fn schedule(&mut self, delay: Duration) {
+        unsafe { add_timer(self.as_ngx_event(), duration_to_msec(delay)) };
+    }
+
+    /// Run the callback at the end of the current event loop iteration.
+    pub fn post(&mut self) {
+        unsafe { post_event(self.as_ngx_event(), posted_events()) };
+    }
+
+    /// Returns `true` if the callback is due to run, after a delay or as a posted event.
+    pub fn is_pending(&self) -> bool {
+        let ev = self.as_ngx_event();
+        unsafe { (*ev).timer_set() != 0 || (*ev).posted() != 0 }
+    }
+
+    /// Cancel the timer (including an interval) and any posted run of the callback.
+    pub fn cancel(&mut self) {
+        self.state.interval.set(None);
+
+        let ev = self.as_ngx_event();
+        unsafe {
+            if (*ev).timer_set() != 0 {
+                del_timer(ev);
+            }
+
+            if (*ev).posted() != 0 {
+                delete_posted_event(ev);
+            }
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+unsafe extern "C" fn timer_handler(ev: *mut ngx_event_t) {
+    // Hold a reference for the duration of the callback, which may drop the timer itself (e.g.
+    // by finalizing the request owning it)
+    let state = (*ev).data as *const TimerState;
+    Rc::increment_strong_count(state);
+    let state = Rc::from_raw(state);
+
+    if (*ev).timedout() != 0 {
+        (*ev).set_timedout(0);
+
+        // Rearm before running the callback, so that it can cancel the interval
+        if let Some(period) = state.interval.get() {
+            add_timer(ev, period);
+        }
+    }
+
+    if let Ok(mut callback) = state.callback.try_borrow_mut() {
+        (*callback)();
+    }
+}
@@ -0,0 +1,80 @@
+use crate::bindings::*;
+use crate::core::*;
+
+use std::slice;
+
+/// Iterator over the decoded `key=value` pairs of a request query string.
+///
+/// Keys and values are URI-unescaped and `+` is decoded as a space. Pairs that need no decoding
+/// are borrowed directly from the request, otherwise a decoded copy is allocated from the request
+/// pool. Iteration stops early if a copy can not be allocated.
+///
+/// See [`Request::query_args`](crate::http::Request::query_args).
+pub struct QueryArgs<'a> {
+    pool: Pool,
+    remaining: &'a [u8],
+}
+
+impl<'a> QueryArgs<'a> {
+    pub(crate) fn new(pool: Pool, args: &'a NgxStr) -> QueryArgs<'a> {
+        QueryArgs { pool, remaining: args.as_bytes() }
+    }
+}
+
+impl<'a> Iterator for QueryArgs<'a> {
+    type Item = (&'a NgxStr, &'a NgxStr);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.remaining.is_empty() {
+                return None;
+            }
+
+            let (pair, rest) = split_once(self.remaining, b'&');
+            self.remaining = rest;
+            if pair.is_empty() {
+                continue;
+            }
+
+            let (key, value) = split_once(pair, b'=');
+            let key = unescape(&mut self.pool, key, true)?;
+            let value = unescape(&mut self.pool, value, true)?;
+
+            return Some((key, value));
+        }
+    }
+}
+
+fn split_once(bytes: &[u8], sep: u8) -> (&[u8], &[u8]) {
+    match bytes.iter().position(|&b| b == sep) {
+        Some(i) => (&bytes[..i], &bytes[i + 1..]),
+        None => (bytes, &[][..]),
+    }
+}
+
+/// Decode a URI-escaped string (and `+` as a space, for a query string component), borrowing
+/// `src` if no decoding is required.
+pub(crate) fn unescape<'a>(pool: &mut Pool, src: &'a [u8], plus_as_space: bool) -> Option<&'a NgxStr> {
+    if !src.iter().any(|&b| b == b'%' || (plus_as_space && b == b'+')) {
+        return Some(src.into());
+    }
+
+    let dst = pool.alloc(src.len()) as *mut u_char;
+    if dst.is_null() {
+        return None;
+    }
+
+    // SAFETY: `dst` points to `src.len()` freshly allocated bytes. `ngx_unescape_uri` never
+    // writes past the byte it has just read, so it's safe to decode in-place.
+    unsafe {
+        for (i, &b) in src.iter().enumerate() {
+            *dst.add(i) = if plus_as_space && b == b'+' { b' ' } else { b };
+        }
+
+        let mut d = dst;
+        let mut s = dst;
+        ngx_unescape_uri(&mut d, &mut s, src.len(), 0);
+
+        Some(slice::from_raw_parts(dst, d.offset_from(dst) as usize).into())
+    }
+}
@@ -0,0 +1,116 @@
+use crate::bindings::*;
+use crate::core::*;
+use crate::event::{spawn, TaskHandle};
+use crate::http::Request;
+
+use std::cell::Cell;
+use std::future::Future;
+use std::rc::Rc;
+
+/// Define a static request handler running an async function.
+///
+/// Handlers are expected to take a single [`AsyncRequest`] argument and return a future of a
+/// [`Status`], which is used to finalize the request once the future completes.
+///
+/// ```ignore
+/// http_async_request_handler!(delay_handler, |mut request: AsyncRequest| async move {
+///     sleep(Duration::from_secs(1)).await;
+///     match request.get_mut() {
+///         Some(request) => send_response(request),
+///         None => ERROR,
+///     }
+/// });
+/// ```
+#[macro_export]
+macro_rules! http_async_request_handler {
+    ( $name: ident, $handler: expr ) => {
+        #[no_mangle]
+        extern "C" fn $name(r: *mut ngx_http_request_t) -> ngx_int_t {
+            let status: Status = unsafe { $crate::http::spawn_request_handler(r, $handler) };
+            status.0
+        }
+    };
+}
+
+/// Handle to the [`Request`] of an async request handler.
+///
+/// The request is freed when the client connection is closed, which cancels the handler's task.
+/// The handle only gives access to the request until then, so [`AsyncRequest::get`] returns
+/// `None` if it's used afterwards (e.g. after being moved out of the task).
+pub struct AsyncRequest {
+    r: *mut ngx_http_request_t,
+    alive: Rc<Cell<bool>>,
+}
+
+impl AsyncRequest {
+    /// Returns `true` if the request hasn't been freed yet.
+    pub fn is_alive(&self) -> bool {
+        self.alive.get()
+    }
+
+    /// The request, unless it was freed.
+    pub fn get(&self) -> Option<&Request> {
+        if !self.is_alive() {
+            return None;
+        }
+        // SAFETY: The request is valid until its pool is freed, which clears `alive`.
+        Some(unsafe { Request::from_ngx_http_request(self.r) })
+    }
+
+    /// The mutable request, unless it was freed.
+    pub fn get_mut(&mut self) -> Option<&mut Request> {
+        if !self.is_alive() {
+            return None;
+        }
+        // SAFETY: The request is valid until its pool is freed, which clears `alive`.
+        Some(unsafe { Request::from_ngx_http_request(self.r) })
+    }
+}
+
+/// Task of an async request handler, stored in the request pool.
+struct RequestTask {
+    task: TaskHandle,
+    alive: Rc<Cell<bool>>,
+}
+
+impl Drop for RequestTask {
+    fn drop(&mut self) {
+        // Drop the future while the request is still valid
+        self.task.cancel();
+        self.alive.set(false);
+    }
+}
+
+/// Run an async request handler on the worker's event loop.
+///
+/// The future is spawned and [`DONE`] is returned, keeping the request alive until the future
+/// completes and the request is finalized with its status. If the request is freed first (e.g.
+/// the client connection is closed), the future is dropped.
+pub unsafe fn spawn_request_handler<F, Fut>(r: *mut ngx_http_request_t, handler: F) -> Status
+where
+    F: FnOnce(AsyncRequest) -> Fut,
+    Fut: Future<Output = Status> + 'static,
+{
+    let alive = Rc::new(Cell::new(true));
+    let future = handler(AsyncRequest { r, alive: alive.clone() });
+
+    let mut task = spawn(async move {
+        let status = future.await;
+
+        let c = (*r).connection;
+        ngx_http_finalize_request(r, status.0);
+        ngx_http_run_posted_requests(c);
+    });
+    task.set_log((*(*r).connection).log);
+
+    // Cancel the task if the request is freed before it completes
+    if Request::from_ngx_http_request(r).pool().allocate(RequestTask { task, alive }).is_null() {
+        return ERROR;
+    }
+
+    // Keep the request alive until it's finalized by the task
+    let main = (*r).main;
+    (*main).set_count((*main).count() + 1);
+
+    DONE
+}
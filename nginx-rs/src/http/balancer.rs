@@ -0,0 +1,345 @@
+use crate::bindings::*;
+use crate::core::*;
+use crate::http::{ngx_http_conf_get_module_srv_conf, Request};
+
+use std::marker::PhantomData;
+use std::mem;
+use std::net::SocketAddr;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+/// Custom upstream [load balancer].
+///
+/// A load balancer is enabled for an `upstream` block by a directive whose handler is
+/// [`LoadBalancer::set_balancer`]:
+///
+/// ```ignore
+/// ngx_command_t {
+///     name: ngx_string!("my_balancer"),
+///     type_: (NGX_HTTP_UPS_CONF|NGX_CONF_NOARGS) as ngx_uint_t,
+///     set: Some(MyBalancer::set_balancer),
+///     conf: NGX_RS_HTTP_SRV_CONF_OFFSET,
+///     offset: 0,
+///     post: ptr::null_mut(),
+/// },
+/// ```
+///
+/// The servers of the block are managed by the built-in round-robin balancer, which also does
+/// the failure accounting. Peers are only selected from the primary servers, so `backup`
+/// servers are not allowed. Shared upstream zones (the `zone` directive) are not supported, since
+/// peers are accessed without the round-robin locks; configuration fails if one is set.
+///
+/// [load balancer]: https://nginx.org/en/docs/dev/development_guide.html#http_load_balancing
+pub trait LoadBalancer: Sized + 'static {
+    /// Per-request balancer state, stored in the request pool.
+    type PeerData;
+
+    /// Create the balancer state for a new upstream request.
+    fn init_peer(request: &mut Request, peers: &mut RoundRobinPeers) -> Option<Self::PeerData>;
+
+    /// Select a peer for a connection attempt.
+    ///
+    /// This is usually done with [`RoundRobinPeers::select`]. Return [`BUSY`] if no peer is
+    /// available.
+    fn get_peer(pc: &mut ngx_peer_connection_t, peers: &mut RoundRobinPeers, data: &mut Self::PeerData) -> Status;
+
+    /// Release the peer after a connection attempt.
+    ///
+    /// `state` is `0` on success or a combination of `NGX_PEER_FAILED` and `NGX_PEER_NEXT`.
+    /// The default implementation does the round-robin failure accounting.
+    fn free_peer(pc: &mut ngx_peer_connection_t, peers: &mut RoundRobinPeers, _data: &mut Self::PeerData, state: ngx_uint_t) {
+        peers.free(pc, state);
+    }
+
+    /// Handler of the directive enabling this load balancer in an `upstream` block.
+    unsafe extern "C" fn set_balancer(cf: *mut ngx_conf_t, _cmd: *mut ngx_command_t, _conf: *mut c_void) -> *mut c_char {
+        let uscf = ngx_http_conf_get_module_srv_conf(cf, &ngx_http_upstream_module) as *mut ngx_http_upstream_srv_conf_t;
+
+        if (*uscf).peer.init_upstream.is_some() {
+            ngx_conf_log_error(NGX_LOG_WARN as ngx_uint_t, cf, 0, "load balancing method redefined\0".as_ptr() as *const c_char);
+        }
+
+        (*uscf).peer.init_upstream = Some(init_upstream::<Self>);
+        (*uscf).flags = (NGX_HTTP_UPSTREAM_CREATE
+            | NGX_HTTP_UPSTREAM_WEIGHT
+            | NGX_HTTP_UPSTREAM_MAX_CONNS
+            | NGX_HTTP_UPSTREAM_MAX_FAILS
+            | NGX_HTTP_UPSTREAM_FAIL_TIMEOUT
+            | NGX_HTTP_UPSTREAM_DOWN) as ngx_uint_t;
+
+        ptr::null_mut()
+    }
+}
+
+/// Balancer state of an upstream request.
+///
+/// The round-robin data must be first, since Nginx passes it to the round-robin SSL session
+/// callbacks.
+#[repr(C)]
+struct PeerState<B: LoadBalancer> {
+    rrp: ngx_http_upstream_rr_peer_data_t,
+    data: Option<B::PeerData>,
+}
+
+unsafe extern "C" fn init_upstream<B: LoadBalancer>(cf: *mut ngx_conf_t, us: *mut ngx_http_upstream_srv_conf_t) -> ngx_int_t {
+    // Peers in a shared zone are updated by all workers, which would need the round-robin locks
+    if !(*us).shm_zone.is_null() {
+        ngx_conf_log_error(NGX_LOG_EMERG as ngx_uint_t, cf, 0, "load balancer does not support \"zone\"\0".as_ptr() as *const c_char);
+        return ERROR.into();
+    }
+
+    if ngx_http_upstream_init_round_robin(cf, us) != NGX_OK as ngx_int_t {
+        return ERROR.into();
+    }
+
+    (*us).peer.init = Some(init_peer::<B>);
+
+    OK.into()
+}
+
+unsafe extern "C" fn init_peer<B: LoadBalancer>(r: *mut ngx_http_request_t, us: *mut ngx_http_upstream_srv_conf_t) -> ngx_int_t {
+    let request = Request::from_ngx_http_request(r);
+
+    let state = request.pool().allocate(PeerState::<B> { rrp: mem::zeroed(), data: None });
+    if state.is_null() {
+        return ERROR.into();
+    }
+
+    // `ngx_http_upstream_init_round_robin_peer` initializes the round-robin data in-place
+    let u = (*r).upstream;
+    (*u).peer.data = state as *mut c_void;
+
+    if ngx_http_upstream_init_round_robin_peer(r, us) != NGX_OK as ngx_int_t {
+        return ERROR.into();
+    }
+
+    (*state).data = B::init_peer(request, RoundRobinPeers::from_ngx_rr_peer_data(&mut (*state).rrp));
+    if (*state).data.is_none() {
+        return ERROR.into();
+    }
+
+    (*u).peer.get = Some(get_peer::<B>);
+    (*u).peer.free = Some(free_peer::<B>);
+
+    OK.into()
+}
+
+unsafe extern "C" fn get_peer<B: LoadBalancer>(pc: *mut ngx_peer_connection_t, data: *mut c_void) -> ngx_int_t {
+    let state = &mut *(data as *mut PeerState<B>);
+    let peers = RoundRobinPeers::from_ngx_rr_peer_data(&mut state.rrp);
+    match state.data.as_mut() {
+        Some(data) => B::get_peer(&mut *pc, peers, data).into(),
+        None => ERROR.into(),
+    }
+}
+
+unsafe extern "C" fn free_peer<B: LoadBalancer>(pc: *mut ngx_peer_connection_t, data: *mut c_void, state: ngx_uint_t) {
+    let peer_state = &mut *(data as *mut PeerState<B>);
+    let peers = RoundRobinPeers::from_ngx_rr_peer_data(&mut peer_state.rrp);
+    if let Some(data) = peer_state.data.as_mut() {
+        B::free_peer(&mut *pc, peers, data, state);
+    }
+}
+
+/// Current time in seconds (as per `ngx_time()`).
+fn ngx_time() -> time_t {
+    // SAFETY: The cached time is always initialized before requests are processed.
+    unsafe { (*ngx_cached_time).sec }
+}
+
+/// Round-robin peer data of an upstream request.
+#[repr(transparent)]
+pub struct RoundRobinPeers(ngx_http_upstream_rr_peer_data_t);
+
+impl RoundRobinPeers {
+    /// Create a [`RoundRobinPeers`] from an [`ngx_http_upstream_rr_peer_data_t`].
+    pub unsafe fn from_ngx_rr_peer_data<'a>(rrp: *mut ngx_http_upstream_rr_peer_data_t) -> &'a mut RoundRobinPeers {
+        // SAFETY: The caller has provided a valid non-null pointer to initialized round-robin
+        // peer data which shares the same representation as `RoundRobinPeers`.
+        &mut *rrp.cast::<RoundRobinPeers>()
+    }
+
+    /// Number of (primary) peers.
+    pub fn len(&self) -> usize {
+        unsafe { (*self.0.peers).number }
+    }
+
+    /// Returns `true` if there are no peers.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the peers, in configuration order.
+    pub fn iter(&self) -> PeerIter {
+        PeerIter { peer: unsafe { (*self.0.peers).peer }, _marker: PhantomData }
+    }
+
+    /// Peer at `index`.
+    pub fn get(&self, index: usize) -> Option<&Peer> {
+        if index >= self.len() {
+            return None;
+        }
+        // SAFETY: Without a shared zone, the round-robin peers are allocated as an array (in
+        // configuration order) whose elements are also linked together.
+        unsafe { Some(&*(*self.0.peers).peer.add(index).cast::<Peer>()) }
+    }
+
+    /// Peer at `index`.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Peer> {
+        let peer = self.get(index)? as *const Peer as *mut Peer;
+        // SAFETY: Peers are owned by the upstream configuration and we hold the peer data mutably.
+        unsafe { Some(&mut *peer) }
+    }
+
+    fn tried_bit(index: usize) -> (usize, usize) {
+        let bits = 8 * mem::size_of::<usize>();
+        (index / bits, 1 << (index % bits))
+    }
+
+    /// Has the peer at `index` already been tried for this request?
+    pub fn is_tried(&self, index: usize) -> bool {
+        let (n, m) = Self::tried_bit(index);
+        unsafe { *self.0.tried.add(n) & m != 0 }
+    }
+
+    /// Mark the peer at `index` as tried for this request.
+    pub fn set_tried(&mut self, index: usize) {
+        let (n, m) = Self::tried_bit(index);
+        unsafe { *self.0.tried.add(n) |= m };
+    }
+
+    /// Is the peer at `index` available (not down, failed or at its connection limit)
+    /// and not yet tried?
+    pub fn is_available(&self, index: usize) -> bool {
+        !self.is_tried(index) && self.get(index).map_or(false, |peer| peer.is_available())
+    }
+
+    /// Connect to the peer at `index`.
+    ///
+    /// This sets the peer address of `pc` and does the same bookkeeping as the round-robin
+    /// balancer, so failures are accounted for by [`RoundRobinPeers::free`].
+    pub fn select(&mut self, pc: &mut ngx_peer_connection_t, index: usize) -> Status {
+        let peer = match self.get_mut(index) {
+            Some(peer) => peer as *mut Peer as *mut ngx_http_upstream_rr_peer_t,
+            None => return ERROR,
+        };
+
+        unsafe {
+            let now = ngx_time();
+
+            self.0.current = peer;
+            self.set_tried(index);
+
+            pc.sockaddr = (*peer).sockaddr;
+            pc.socklen = (*peer).socklen;
+            pc.name = &mut (*peer).name;
+
+            (*peer).conns += 1;
+
+            if now - (*peer).checked > (*peer).fail_timeout {
+                (*peer).checked = now;
+            }
+        }
+
+        OK
+    }
+
+    /// Select a peer using the built-in round-robin algorithm.
+    pub fn get_round_robin(&mut self, pc: &mut ngx_peer_connection_t) -> Status {
+        let rrp = &mut self.0 as *mut ngx_http_upstream_rr_peer_data_t as *mut c_void;
+        unsafe {
+            Status(ngx_http_upstream_get_round_robin_peer(pc, rrp))
+        }
+    }
+
+    /// Release the current peer, updating its failure accounting.
+    pub fn free(&mut self, pc: &mut ngx_peer_connection_t, state: ngx_uint_t) {
+        let rrp = &mut self.0 as *mut ngx_http_upstream_rr_peer_data_t as *mut c_void;
+        unsafe {
+            ngx_http_upstream_free_round_robin_peer(pc, rrp, state);
+        }
+    }
+}
+
+/// Iterator over [`RoundRobinPeers`].
+pub struct PeerIter<'a> {
+    peer: *mut ngx_http_upstream_rr_peer_t,
+    _marker: PhantomData<&'a Peer>,
+}
+
+impl<'a> Iterator for PeerIter<'a> {
+    type Item = &'a Peer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.peer.is_null() {
+            return None;
+        }
+
+        // SAFETY: The peer list is a valid linked list terminated by a null pointer.
+        unsafe {
+            let peer = &*self.peer.cast::<Peer>();
+            self.peer = (*self.peer).next;
+            Some(peer)
+        }
+    }
+}
+
+/// An upstream server (as a round-robin peer).
+#[repr(transparent)]
+pub struct Peer(ngx_http_upstream_rr_peer_t);
+
+impl Peer {
+    /// Peer address in text form.
+    pub fn name(&self) -> &NgxStr {
+        unsafe { NgxStr::from_ngx_str(self.0.name) }
+    }
+
+    /// Name of the `server` this peer was resolved from.
+    pub fn server(&self) -> &NgxStr {
+        unsafe { NgxStr::from_ngx_str(self.0.server) }
+    }
+
+    /// Peer address.
+    pub fn addr(&self) -> Option<SocketAddr> {
+        unsafe { sockaddr_to_socket_addr(self.0.sockaddr) }
+    }
+
+    /// Configured weight.
+    pub fn weight(&self) -> ngx_int_t {
+        self.0.weight
+    }
+
+    /// Number of active connections.
+    pub fn conns(&self) -> ngx_uint_t {
+        self.0.conns
+    }
+
+    /// Number of failures in the current `fail_timeout` period.
+    pub fn fails(&self) -> ngx_uint_t {
+        self.0.fails
+    }
+
+    /// Is the peer marked as `down`?
+    pub fn is_down(&self) -> bool {
+        self.0.down != 0
+    }
+
+    /// Is the peer available (not down, failed or at its connection limit)?
+    pub fn is_available(&self) -> bool {
+        let peer = &self.0;
+
+        if peer.down != 0 {
+            return false;
+        }
+
+        if peer.max_fails != 0 && peer.fails >= peer.max_fails && ngx_time() - peer.checked <= peer.fail_timeout {
+            return false;
+        }
+
+        if peer.max_conns != 0 && peer.conns >= peer.max_conns {
+            return false;
+        }
+
+        true
+    }
+}
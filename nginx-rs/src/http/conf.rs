@@ -1,6 +1,7 @@
 use crate::bindings::*;
 
 use std::os::raw::c_void;
+use std::ptr;
 
 pub unsafe fn ngx_http_conf_get_module_main_conf(cf: *mut ngx_conf_t, module: &ngx_module_t)  -> *mut c_void {
     let http_conf_ctx = (*cf).ctx as *mut ngx_http_conf_ctx_t;
@@ -16,3 +17,11 @@ pub unsafe fn ngx_http_conf_get_module_loc_conf(cf: *mut ngx_conf_t, module: &ng
     let http_conf_ctx = (*cf).ctx as *mut ngx_http_conf_ctx_t;
     *(*http_conf_ctx).loc_conf.add(module.ctx_index)
 }
+
+pub unsafe fn ngx_http_cycle_get_module_main_conf(cycle: *mut ngx_cycle_t, module: &ngx_module_t) -> *mut c_void {
+    let http_conf_ctx = *(*cycle).conf_ctx.add(ngx_http_module.index) as *mut ngx_http_conf_ctx_t;
+    if http_conf_ctx.is_null() {
+        return ptr::null_mut();
+    }
+    *(*http_conf_ctx).main_conf.add(module.ctx_index)
+}
@@ -0,0 +1,185 @@
+use crate::bindings::*;
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// `SameSite` attribute of a [`SetCookie`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl SameSite {
+    fn as_str(self) -> &'static str {
+        match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+}
+
+/// Builder for a [`Set-Cookie`] response header.
+///
+/// ```ignore
+/// let cookie = SetCookie::new("session", "abc123")
+///     .path("/")
+///     .max_age(Duration::from_secs(3600))
+///     .secure(true)
+///     .http_only(true)
+///     .same_site(SameSite::Lax);
+///
+/// request.add_set_cookie(&cookie)?;
+/// ```
+///
+/// [`Set-Cookie`]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Set-Cookie
+#[derive(Clone, Debug)]
+pub struct SetCookie<'a> {
+    name: &'a str,
+    value: &'a str,
+    path: Option<&'a str>,
+    domain: Option<&'a str>,
+    max_age: Option<Duration>,
+    expires: Option<SystemTime>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl<'a> SetCookie<'a> {
+    /// Create a new cookie with the given `name` and `value`.
+    pub fn new(name: &'a str, value: &'a str) -> SetCookie<'a> {
+        SetCookie {
+            name,
+            value,
+            path: None,
+            domain: None,
+            max_age: None,
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    /// Set the `Path` attribute.
+    pub fn path(mut self, path: &'a str) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Set the `Domain` attribute.
+    pub fn domain(mut self, domain: &'a str) -> Self {
+        self.domain = Some(domain);
+        self
+    }
+
+    /// Set the `Max-Age` attribute (in whole seconds).
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Set the `Expires` attribute.
+    pub fn expires(mut self, expires: SystemTime) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    /// Set the `Secure` flag.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Set the `HttpOnly` flag.
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// Set the `SameSite` attribute.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Is this cookie well-formed according to [RFC 6265]?
+    ///
+    /// The name must be a non-empty token and the value (optionally quoted) must only contain
+    /// `cookie-octet`s. Attribute values must not contain control characters or `;`.
+    ///
+    /// [RFC 6265]: https://tools.ietf.org/html/rfc6265#section-4.1.1
+    pub fn is_valid(&self) -> bool {
+        let value = self.value.as_bytes();
+        let value = if value.len() >= 2 && value[0] == b'"' && value[value.len() - 1] == b'"' {
+            &value[1..value.len() - 1]
+        } else {
+            value
+        };
+
+        !self.name.is_empty()
+            && self.name.bytes().all(is_token_char)
+            && value.iter().all(|&b| is_cookie_octet(b))
+            && self.path.map_or(true, is_attribute_value)
+            && self.domain.map_or(true, is_attribute_value)
+    }
+}
+
+impl fmt::Display for SetCookie<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+
+        if let Some(path) = self.path {
+            write!(f, "; Path={}", path)?;
+        }
+
+        if let Some(domain) = self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+
+        if let Some(expires) = self.expires {
+            let secs = expires.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+            let mut buf = [0u8; 64];
+            // SAFETY: `ngx_http_cookie_time` writes at most `sizeof("Thu, 01-Jan-1970 00:00:00 GMT")`
+            // bytes, which fits in `buf`.
+            let expires = unsafe {
+                let end = ngx_http_cookie_time(buf.as_mut_ptr(), secs as time_t);
+                &buf[..end.offset_from(buf.as_ptr()) as usize]
+            };
+            write!(f, "; Expires={}", String::from_utf8_lossy(expires))?;
+        }
+
+        if self.secure {
+            f.write_str("; Secure")?;
+        }
+
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site.as_str())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_token_char(b: u8) -> bool {
+    b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b)
+}
+
+fn is_cookie_octet(b: u8) -> bool {
+    b.is_ascii_graphic() && !b"\",;\\".contains(&b)
+}
+
+fn is_attribute_value(s: &str) -> bool {
+    s.bytes().all(|b| !b.is_ascii_control() && b != b';')
+}
@@ -0,0 +1,36 @@
+use crate::bindings::*;
+use crate::core::*;
+
+/// Iterator over a list of HTTP headers ([`ngx_table_elt_t`]).
+///
+/// Headers removed from the list (with a zero `hash`) are skipped.
+///
+/// [`ngx_table_elt_t`]: https://nginx.org/en/docs/dev/development_guide.html#http_request
+pub struct HeadersIter<'a>(ListIter<'a, ngx_table_elt_t>);
+
+impl<'a> HeadersIter<'a> {
+    /// Iterate over the headers of an [`ngx_list_t`] of [`ngx_table_elt_t`].
+    ///
+    /// [`ngx_list_t`]: https://nginx.org/en/docs/dev/development_guide.html#list
+    /// [`ngx_table_elt_t`]: https://nginx.org/en/docs/dev/development_guide.html#http_request
+    pub unsafe fn from_ngx_list(list: &'a ngx_list_t) -> HeadersIter<'a> {
+        HeadersIter(NgxList::<ngx_table_elt_t>::from_ngx_list_ref(list).iter())
+    }
+}
+
+impl<'a> Iterator for HeadersIter<'a> {
+    type Item = (&'a NgxStr, &'a NgxStr);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let h = self.0.find(|h| h.hash != 0)?;
+        // SAFETY: Header keys and values are valid Nginx strings.
+        unsafe { Some((NgxStr::from_ngx_str(h.key), NgxStr::from_ngx_str(h.value))) }
+    }
+}
+
+/// Find the value of the first header called `name` (ignoring case).
+pub(crate) fn find_header<'a>(mut headers: HeadersIter<'a>, name: &str) -> Option<&'a NgxStr> {
+    headers
+        .find(|(key, _)| key.as_bytes().eq_ignore_ascii_case(name.as_bytes()))
+        .map(|(_, value)| value)
+}
@@ -0,0 +1,73 @@
+use crate::bindings::*;
+
+/// HTTP request method.
+///
+/// See [`ngx_http_request_t`] `method` field for the Nginx representation.
+///
+/// [`ngx_http_request_t`]: https://nginx.org/en/docs/dev/development_guide.html#http_request
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Method {
+    Unknown,
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Mkcol,
+    Copy,
+    Move,
+    Options,
+    Propfind,
+    Proppatch,
+    Lock,
+    Unlock,
+    Patch,
+    Trace,
+}
+
+impl Method {
+    /// Nginx method bit (e.g. `NGX_HTTP_GET`) for this method.
+    pub fn as_ngx_method(self) -> ngx_uint_t {
+        (match self {
+            Method::Unknown => NGX_HTTP_UNKNOWN,
+            Method::Get => NGX_HTTP_GET,
+            Method::Head => NGX_HTTP_HEAD,
+            Method::Post => NGX_HTTP_POST,
+            Method::Put => NGX_HTTP_PUT,
+            Method::Delete => NGX_HTTP_DELETE,
+            Method::Mkcol => NGX_HTTP_MKCOL,
+            Method::Copy => NGX_HTTP_COPY,
+            Method::Move => NGX_HTTP_MOVE,
+            Method::Options => NGX_HTTP_OPTIONS,
+            Method::Propfind => NGX_HTTP_PROPFIND,
+            Method::Proppatch => NGX_HTTP_PROPPATCH,
+            Method::Lock => NGX_HTTP_LOCK,
+            Method::Unlock => NGX_HTTP_UNLOCK,
+            Method::Patch => NGX_HTTP_PATCH,
+            Method::Trace => NGX_HTTP_TRACE,
+        }) as ngx_uint_t
+    }
+}
+
+impl From<ngx_uint_t> for Method {
+    fn from(method: ngx_uint_t) -> Self {
+        match method as u32 {
+            NGX_HTTP_GET => Method::Get,
+            NGX_HTTP_HEAD => Method::Head,
+            NGX_HTTP_POST => Method::Post,
+            NGX_HTTP_PUT => Method::Put,
+            NGX_HTTP_DELETE => Method::Delete,
+            NGX_HTTP_MKCOL => Method::Mkcol,
+            NGX_HTTP_COPY => Method::Copy,
+            NGX_HTTP_MOVE => Method::Move,
+            NGX_HTTP_OPTIONS => Method::Options,
+            NGX_HTTP_PROPFIND => Method::Propfind,
+            NGX_HTTP_PROPPATCH => Method::Proppatch,
+            NGX_HTTP_LOCK => Method::Lock,
+            NGX_HTTP_UNLOCK => Method::Unlock,
+            NGX_HTTP_PATCH => Method::Patch,
+            NGX_HTTP_TRACE => Method::Trace,
+            _ => Method::Unknown,
+        }
+    }
+}
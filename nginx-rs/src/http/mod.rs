@@ -1,9 +1,25 @@
+mod args;
+#[cfg(feature = "async")]
+mod async_request;
+mod balancer;
 mod conf;
+mod cookie;
+mod headers;
 mod status;
+mod method;
 mod module;
 mod request;
+mod upstream;
 
+pub use args::*;
+#[cfg(feature = "async")]
+pub use async_request::*;
+pub use balancer::*;
 pub use conf::*;
+pub use cookie::*;
+pub use headers::*;
 pub use status::*;
+pub use method::*;
 pub use module::*;
 pub use request::*;
+pub use upstream::*;
@@ -2,7 +2,12 @@ use crate::{bindings::*, ngx_null_string};
 use crate::core::*;
 
 use crate::http::status::*;
+use crate::http::method::*;
+use crate::http::args::*;
+use crate::http::cookie::*;
+use crate::http::headers::*;
 
+use std::mem;
 use std::os::raw::c_void;
 
 /// Define a static request handler.
@@ -46,11 +51,14 @@ impl Request {
         }
     }
 
-    /// Pointer to a [`ngx_connection_t`] client connection object.
+    /// Client [connection].
     ///
-    /// [`ngx_connection_t`]: https://nginx.org/en/docs/dev/development_guide.html#connection
-    pub fn connection(&self) -> *mut ngx_connection_t {
-        self.0.connection
+    /// [connection]: https://nginx.org/en/docs/dev/development_guide.html#connection
+    pub fn connection(&self) -> &Connection {
+        // SAFETY: Every request has a valid client connection.
+        unsafe {
+            Connection::from_ngx_connection(self.0.connection)
+        }
     }
 
     /// Module location configuration.
@@ -87,6 +95,107 @@ impl Request {
         }
     }
 
+    /// Request method.
+    pub fn method(&self) -> Method {
+        Method::from(self.0.method)
+    }
+
+    /// Is the request method one of `methods`?
+    ///
+    /// Useful for implementing method allow-lists.
+    pub fn method_is(&self, methods: &[Method]) -> bool {
+        methods.iter().any(|m| m.as_ngx_method() & self.0.method != 0)
+    }
+
+    /// Request method name as sent by the client.
+    pub fn method_name(&self) -> &NgxStr {
+        unsafe {
+            NgxStr::from_ngx_str(self.0.method_name)
+        }
+    }
+
+    /// Request URI path (without arguments).
+    ///
+    /// This is the normalized URI, which may be changed by internal redirects.
+    pub fn uri(&self) -> &NgxStr {
+        unsafe {
+            NgxStr::from_ngx_str(self.0.uri)
+        }
+    }
+
+    /// Original request URI as sent by the client (with arguments).
+    pub fn unparsed_uri(&self) -> &NgxStr {
+        unsafe {
+            NgxStr::from_ngx_str(self.0.unparsed_uri)
+        }
+    }
+
+    /// Request arguments (the query string, without the leading `?`).
+    pub fn args(&self) -> &NgxStr {
+        unsafe {
+            NgxStr::from_ngx_str(self.0.args)
+        }
+    }
+
+    /// Value of the request argument `name` (e.g. `bar` for `foo=bar`).
+    ///
+    /// The argument name is matched case-insensitively and the value is returned as-is,
+    /// without URI-unescaping. Use [`Request::query_args`] for decoded values.
+    pub fn arg(&self, name: &str) -> Option<&NgxStr> {
+        let r = (self as *const Request as *mut Request).cast();
+        // SAFETY: `ngx_http_arg` does not mutate `r` or `name` and stores a slice of the request
+        // arguments in `value` if it finds the argument.
+        unsafe {
+            let mut value = ngx_null_string!();
+            if ngx_http_arg(r, name.as_ptr() as *mut u_char, name.len(), &mut value) != NGX_OK as ngx_int_t {
+                return None;
+            }
+            Some(NgxStr::from_ngx_str(value))
+        }
+    }
+
+    /// Iterate over the decoded `key=value` pairs of the request arguments.
+    pub fn query_args(&self) -> QueryArgs {
+        QueryArgs::new(self.pool(), self.args())
+    }
+
+    /// Extension of the request URI path (e.g. `html` for `/index.html`).
+    pub fn exten(&self) -> &NgxStr {
+        unsafe {
+            NgxStr::from_ngx_str(self.0.exten)
+        }
+    }
+
+    /// Request HTTP protocol (e.g. `HTTP/1.1`).
+    ///
+    /// This is empty for HTTP/0.9 requests.
+    pub fn http_protocol(&self) -> &NgxStr {
+        unsafe {
+            NgxStr::from_ngx_str(self.0.http_protocol)
+        }
+    }
+
+    /// Request HTTP version (e.g. `NGX_HTTP_VERSION_11`).
+    pub fn http_version(&self) -> ngx_uint_t {
+        self.0.http_version
+    }
+
+    /// Complete request line as sent by the client.
+    pub fn request_line(&self) -> &NgxStr {
+        unsafe {
+            NgxStr::from_ngx_str(self.0.request_line)
+        }
+    }
+
+    /// URI scheme of absolute request URIs (e.g. `http` for `GET http://example.com/`).
+    ///
+    /// This is empty for the usual origin-form requests.
+    pub fn schema(&self) -> &NgxStr {
+        unsafe {
+            NgxStr::from_ngx_str(self.0.schema)
+        }
+    }
+
     /// Client HTTP [User-Agent].
     ///
     /// [User-Agent]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/User-Agent
@@ -96,6 +205,65 @@ impl Request {
         }
     }
 
+    /// Iterate over the request headers.
+    pub fn headers_in(&self) -> HeadersIter {
+        unsafe { HeadersIter::from_ngx_list(&self.0.headers_in.headers) }
+    }
+
+    /// Value of the first request header called `name` (ignoring case).
+    pub fn header_in(&self, name: &str) -> Option<&NgxStr> {
+        find_header(self.headers_in(), name)
+    }
+
+    /// Value of the request cookie `name`.
+    ///
+    /// If the client sent multiple cookies with the same name, the first one is returned.
+    pub fn cookie(&self, name: &str) -> Option<&NgxStr> {
+        let r = (self as *const Request as *mut Request).cast();
+        let mut name = ngx_str_t { len: name.len(), data: name.as_ptr() as *mut u_char };
+        // SAFETY: `ngx_http_parse_multi_header_lines` does not mutate `r` or `name` and stores a
+        // slice of the matching `Cookie` header in `value` if it finds the cookie.
+        unsafe {
+            let mut value = ngx_null_string!();
+            let h = ngx_http_parse_multi_header_lines(r, self.0.headers_in.cookie, &mut name, &mut value);
+            if h.is_null() {
+                return None;
+            }
+            Some(NgxStr::from_ngx_str(value))
+        }
+    }
+
+    /// Add a response header.
+    ///
+    /// The header name and value are copied into the request pool.
+    pub fn add_header_out(&mut self, key: &str, value: &str) -> Result<(), ()> {
+        let mut pool = self.pool();
+        let key = pool.create_ngx_str(key.as_bytes()).ok_or(())?;
+        let value = pool.create_ngx_str(value.as_bytes()).ok_or(())?;
+
+        // SAFETY: A zeroed header has no lowercase key and no next header.
+        let headers = unsafe { NgxList::<ngx_table_elt_t>::from_ngx_list(&mut self.0.headers_out.headers) };
+        let h = headers.push(unsafe { mem::zeroed() }).ok_or(())?;
+        h.hash = 1;
+        h.key = key;
+        h.value = value;
+
+        Ok(())
+    }
+
+    /// Add a [`Set-Cookie`] response header.
+    ///
+    /// Fails if the cookie is not well-formed (see [`SetCookie::is_valid`]).
+    ///
+    /// [`Set-Cookie`]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Set-Cookie
+    pub fn add_set_cookie(&mut self, cookie: &SetCookie) -> Result<(), ()> {
+        if !cookie.is_valid() {
+            return Err(());
+        }
+
+        self.add_header_out("Set-Cookie", &cookie.to_string())
+    }
+
     /// Set HTTP status of response.
     pub fn set_status(&mut self, status: HTTPStatus) {
         self.0.headers_out.status = status.into();
@@ -135,4 +303,58 @@ impl Request {
             Status(ngx_http_output_filter(&mut self.0, body))
         }
     }
+
+    /// Run blocking `work` on a [`ThreadPool`], then finalize the request with the status
+    /// returned by `complete`.
+    ///
+    /// The result given to `complete` is `None` if `work` panicked. The request is kept alive
+    /// until it's finalized, so the handler should return the result of this function. If the
+    /// client connection fails meanwhile, the request is finalized without calling `complete`.
+    #[cfg(feature = "thread_pool")]
+    pub fn spawn_blocking<T, F, C>(&mut self, thread_pool: ThreadPool, work: F, complete: C) -> Status
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+        C: FnOnce(&mut Request, Option<T>) -> Status + 'static,
+    {
+        let r = &mut self.0 as *mut ngx_http_request_t;
+        let log = self.connection().log();
+        let main = self.0.main;
+
+        // Like the built-in thread handlers, block the request so that it isn't freed (e.g. by
+        // `ngx_http_terminate_request`) while the task is running
+        unsafe {
+            (*main).set_blocked((*main).blocked() + 1);
+            self.0.set_aio(1);
+        }
+
+        let posted = thread_pool.post(&mut self.pool(), log, work, move |result| unsafe {
+            let c = (*r).connection;
+
+            (*(*r).main).set_blocked((*(*r).main).blocked() - 1);
+            (*r).set_aio(0);
+
+            if (*c).error() != 0 {
+                ngx_http_finalize_request(r, NGX_HTTP_CLIENT_CLOSED_REQUEST as ngx_int_t);
+            } else {
+                let status = complete(Request::from_ngx_http_request(r), result);
+                ngx_http_finalize_request(r, status.0);
+            }
+
+            ngx_http_run_posted_requests(c);
+        });
+
+        if posted.is_err() {
+            unsafe {
+                (*main).set_blocked((*main).blocked() - 1);
+                self.0.set_aio(0);
+            }
+            return ERROR;
+        }
+
+        // Keep the request alive until it's finalized on completion
+        unsafe { (*main).set_count((*main).count() + 1) };
+
+        DONE
+    }
 }
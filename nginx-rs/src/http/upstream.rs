@@ -0,0 +1,460 @@
+use crate::bindings::*;
+use crate::core::*;
+use crate::event::current_msec;
+use crate::ngx_null_string;
+use crate::http::{find_header, HeadersIter, Merge, Request};
+
+use std::mem;
+use std::net::SocketAddr;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::time::Duration;
+
+/// Callbacks of a custom [upstream] protocol.
+///
+/// These are installed on a request by [`Request::upstream_create`]. The callbacks follow the
+/// Nginx conventions, see the memcached and FastCGI modules for examples.
+///
+/// [upstream]: https://nginx.org/en/docs/dev/development_guide.html#http_load_balancing
+pub trait Upstream {
+    /// Create the request to send to the upstream server in `r->upstream->request_bufs`.
+    unsafe extern "C" fn create_request(r: *mut ngx_http_request_t) -> ngx_int_t;
+
+    /// Reset the state before retrying the request with the next upstream server.
+    unsafe extern "C" fn reinit_request(_r: *mut ngx_http_request_t) -> ngx_int_t {
+        OK.into()
+    }
+
+    /// Parse the upstream response header in `r->upstream->buffer`.
+    ///
+    /// Returns [`AGAIN`] if the header is incomplete, or [`OK`] after setting
+    /// `r->upstream->headers_in`.
+    unsafe extern "C" fn process_header(r: *mut ngx_http_request_t) -> ngx_int_t;
+
+    /// Called if the client aborts the request.
+    unsafe extern "C" fn abort_request(_r: *mut ngx_http_request_t) {}
+
+    /// Called once the upstream request is complete.
+    unsafe extern "C" fn finalize_request(_r: *mut ngx_http_request_t, _rc: ngx_int_t) {}
+
+    /// Initialize the response body filter.
+    ///
+    /// `data` is the request.
+    unsafe extern "C" fn input_filter_init(_data: *mut c_void) -> ngx_int_t {
+        OK.into()
+    }
+
+    /// Process `bytes` newly received bytes of the response body at `r->upstream->buffer.last`.
+    ///
+    /// `data` is the request. The default implementation passes the body through as-is, limited
+    /// to `r->upstream->length` bytes (if set), just like the built-in non-buffered filter.
+    unsafe extern "C" fn input_filter(data: *mut c_void, bytes: ssize_t) -> ngx_int_t {
+        let r = data as *mut ngx_http_request_t;
+        let u = (*r).upstream;
+
+        if (*u).length == 0 {
+            return OK.into();
+        }
+
+        let mut ll = &mut (*u).out_bufs as *mut *mut ngx_chain_t;
+        while !(*ll).is_null() {
+            ll = &mut (**ll).next;
+        }
+
+        let cl = ngx_chain_get_free_buf((*r).pool, &mut (*u).free_bufs);
+        if cl.is_null() {
+            return ERROR.into();
+        }
+
+        *ll = cl;
+
+        let buf = (*cl).buf;
+        (*buf).set_flush(1);
+        (*buf).set_memory(1);
+        (*buf).pos = (*u).buffer.last;
+        (*u).buffer.last = (*u).buffer.last.offset(bytes);
+        (*buf).last = (*u).buffer.last;
+        (*buf).tag = (*u).output.tag;
+
+        if (*u).length == -1 {
+            return OK.into();
+        }
+
+        if bytes as off_t > (*u).length {
+            (*buf).last = (*buf).pos.offset((*u).length as isize);
+            (*u).length = 0;
+            return OK.into();
+        }
+
+        (*u).length -= bytes as off_t;
+
+        OK.into()
+    }
+}
+
+/// Upstream configuration ([`ngx_http_upstream_conf_t`]) for use in a location configuration.
+///
+/// The defaults match those of the memcached module: the response is not buffered and the
+/// client request headers and body are not passed to the upstream. Common directives are handled
+/// by the `set_*` methods, other fields can be adjusted through
+/// [`UpstreamConf::as_ngx_upstream_conf_mut`] in the configuration handlers.
+///
+/// Besides [`Merge::merge`], `merge_loc_conf` must call [`UpstreamConf::merge_hide_headers`],
+/// which builds the hash of response headers not passed to the client:
+///
+/// ```ignore
+/// unsafe extern "C" fn merge_loc_conf(cf: *mut ngx_conf_t, prev: *mut c_void, conf: *mut c_void) -> *mut c_char {
+///     let prev = &mut *(prev as *mut LocConf);
+///     let conf = &mut *(conf as *mut LocConf);
+///     conf.upstream.merge(&prev.upstream);
+///     conf.upstream.merge_hide_headers(cf, &mut prev.upstream, &["Date", "Server"])
+/// }
+/// ```
+///
+/// [`ngx_http_upstream_conf_t`]: https://nginx.org/en/docs/http/ngx_http_upstream_module.html
+#[repr(transparent)]
+pub struct UpstreamConf(ngx_http_upstream_conf_t);
+
+impl UpstreamConf {
+    /// Pointer to the underlying [`ngx_http_upstream_conf_t`].
+    pub fn as_ngx_upstream_conf(&self) -> *mut ngx_http_upstream_conf_t {
+        &self.0 as *const _ as *mut _
+    }
+
+    /// Mutable access to the underlying [`ngx_http_upstream_conf_t`].
+    pub fn as_ngx_upstream_conf_mut(&mut self) -> &mut ngx_http_upstream_conf_t {
+        &mut self.0
+    }
+
+    /// Handle a `*_pass` directive.
+    ///
+    /// The first directive argument is either the address of a server or the name of
+    /// an `upstream` block.
+    pub unsafe fn set_pass(&mut self, cf: *mut ngx_conf_t) -> *mut c_char {
+        if !self.0.upstream.is_null() {
+            return "is duplicate\0".as_ptr() as *mut c_char;
+        }
+
+        let args = NgxArray::<ngx_str_t>::from_ngx_array((*cf).args).as_slice();
+
+        let mut url: ngx_url_t = mem::zeroed();
+        url.url = args[1];
+        url.set_no_resolve(1);
+
+        self.0.upstream = ngx_http_upstream_add(cf, &mut url, 0);
+        if self.0.upstream.is_null() {
+            return NGX_CONF_ERROR;
+        }
+
+        ptr::null_mut()
+    }
+
+    /// Handle a `*_connect_timeout` directive.
+    pub unsafe fn set_connect_timeout(&mut self, cf: *mut ngx_conf_t) -> *mut c_char {
+        set_msec(cf, &mut self.0.connect_timeout)
+    }
+
+    /// Handle a `*_send_timeout` directive.
+    pub unsafe fn set_send_timeout(&mut self, cf: *mut ngx_conf_t) -> *mut c_char {
+        set_msec(cf, &mut self.0.send_timeout)
+    }
+
+    /// Handle a `*_read_timeout` directive.
+    pub unsafe fn set_read_timeout(&mut self, cf: *mut ngx_conf_t) -> *mut c_char {
+        set_msec(cf, &mut self.0.read_timeout)
+    }
+
+    /// Handle a `*_next_upstream_timeout` directive.
+    pub unsafe fn set_next_upstream_timeout(&mut self, cf: *mut ngx_conf_t) -> *mut c_char {
+        set_msec(cf, &mut self.0.next_upstream_timeout)
+    }
+
+    /// Handle a `*_next_upstream_tries` directive.
+    pub unsafe fn set_next_upstream_tries(&mut self, cf: *mut ngx_conf_t) -> *mut c_char {
+        if self.0.next_upstream_tries != NGX_CONF_UNSET_UINT {
+            return "is duplicate\0".as_ptr() as *mut c_char;
+        }
+
+        let value = directive_arg(cf);
+        let tries = ngx_atoi(value.data, value.len);
+        if tries == NGX_ERROR as ngx_int_t {
+            return "invalid number\0".as_ptr() as *mut c_char;
+        }
+
+        self.0.next_upstream_tries = tries as ngx_uint_t;
+        ptr::null_mut()
+    }
+
+    /// Handle a `*_buffer_size` directive.
+    pub unsafe fn set_buffer_size(&mut self, cf: *mut ngx_conf_t) -> *mut c_char {
+        if self.0.buffer_size != NGX_CONF_UNSET_SIZE {
+            return "is duplicate\0".as_ptr() as *mut c_char;
+        }
+
+        let mut value = directive_arg(cf);
+        let size = ngx_parse_size(&mut value);
+        if size == NGX_ERROR as ssize_t {
+            return "invalid value\0".as_ptr() as *mut c_char;
+        }
+
+        self.0.buffer_size = size as usize;
+        ptr::null_mut()
+    }
+
+    /// Build the hash of upstream response headers not passed to the client, or inherit it from
+    /// `prev` (`ngx_http_upstream_hide_headers_hash`).
+    ///
+    /// Nginx looks up every upstream response header in this hash, so this must be called from
+    /// `merge_loc_conf`. `hide_headers` are never passed to the client, like those in the
+    /// `hide_headers` array of the configuration (e.g. from a `*_hide_header` directive).
+    pub unsafe fn merge_hide_headers(&mut self, cf: *mut ngx_conf_t, prev: &mut UpstreamConf, hide_headers: &[&str]) -> *mut c_char {
+        // Terminated by an empty string
+        let mut default_hide_headers: Vec<ngx_str_t> = hide_headers
+            .iter()
+            .map(|h| ngx_str_t { len: h.len(), data: h.as_ptr() as *mut u_char })
+            .chain(Some(ngx_null_string!()))
+            .collect();
+
+        let mut hash: ngx_hash_init_t = mem::zeroed();
+        hash.max_size = 512;
+        hash.bucket_size = align(64, ngx_cacheline_size);
+        hash.name = "upstream_hide_headers_hash\0".as_ptr() as *mut c_char;
+
+        let rc = ngx_http_upstream_hide_headers_hash(cf, &mut self.0, &mut prev.0, default_hide_headers.as_mut_ptr(), &mut hash);
+        if rc != NGX_OK as ngx_int_t {
+            return NGX_CONF_ERROR;
+        }
+
+        ptr::null_mut()
+    }
+}
+
+/// First argument of the directive being handled.
+unsafe fn directive_arg(cf: *mut ngx_conf_t) -> ngx_str_t {
+    NgxArray::<ngx_str_t>::from_ngx_array((*cf).args).as_slice()[1]
+}
+
+/// Handle a time directive, like `ngx_conf_set_msec_slot`.
+unsafe fn set_msec(cf: *mut ngx_conf_t, field: &mut ngx_msec_t) -> *mut c_char {
+    if *field != NGX_CONF_UNSET_MSEC {
+        return "is duplicate\0".as_ptr() as *mut c_char;
+    }
+
+    let mut value = directive_arg(cf);
+    let msec = ngx_parse_time(&mut value, 0);
+    if msec == NGX_ERROR as ngx_int_t {
+        return "invalid value\0".as_ptr() as *mut c_char;
+    }
+
+    *field = msec as ngx_msec_t;
+    ptr::null_mut()
+}
+
+fn align(size: usize, alignment: usize) -> usize {
+    (size + alignment - 1) & !(alignment - 1)
+}
+
+impl Default for UpstreamConf {
+    fn default() -> Self {
+        // SAFETY: `ngx_http_upstream_conf_t` is a plain C struct, for which zero is the
+        // usual initial value (as if allocated with `ngx_pcalloc`).
+        let mut conf: ngx_http_upstream_conf_t = unsafe { mem::zeroed() };
+
+        conf.local = NGX_CONF_UNSET_PTR as *mut _;
+        conf.hide_headers = NGX_CONF_UNSET_PTR as *mut _;
+        conf.pass_headers = NGX_CONF_UNSET_PTR as *mut _;
+        conf.next_upstream_tries = NGX_CONF_UNSET_UINT;
+        conf.connect_timeout = NGX_CONF_UNSET_MSEC;
+        conf.send_timeout = NGX_CONF_UNSET_MSEC;
+        conf.read_timeout = NGX_CONF_UNSET_MSEC;
+        conf.next_upstream_timeout = NGX_CONF_UNSET_MSEC;
+        conf.buffer_size = NGX_CONF_UNSET_SIZE;
+
+        // the hardcoded values
+        conf.cyclic_temp_file = 0;
+        conf.buffering = 0;
+        conf.ignore_client_abort = 0;
+        conf.send_lowat = 0;
+        conf.bufs.num = 0;
+        conf.busy_buffers_size = 0;
+        conf.max_temp_file_size = 0;
+        conf.temp_file_write_size = 0;
+        conf.intercept_errors = 1;
+        conf.set_intercept_404(1);
+        conf.pass_request_headers = 0;
+        conf.pass_request_body = 0;
+        conf.force_ranges = 1;
+
+        UpstreamConf(conf)
+    }
+}
+
+impl Merge for UpstreamConf {
+    fn merge(&mut self, prev: &UpstreamConf) {
+        let conf = &mut self.0;
+        let prev = &prev.0;
+
+        if conf.local == NGX_CONF_UNSET_PTR as *mut _ {
+            conf.local = if prev.local == NGX_CONF_UNSET_PTR as *mut _ { ptr::null_mut() } else { prev.local };
+        }
+
+        merge_value(&mut conf.next_upstream_tries, prev.next_upstream_tries, NGX_CONF_UNSET_UINT, 0);
+        merge_value(&mut conf.connect_timeout, prev.connect_timeout, NGX_CONF_UNSET_MSEC, 60000);
+        merge_value(&mut conf.send_timeout, prev.send_timeout, NGX_CONF_UNSET_MSEC, 60000);
+        merge_value(&mut conf.read_timeout, prev.read_timeout, NGX_CONF_UNSET_MSEC, 60000);
+        merge_value(&mut conf.next_upstream_timeout, prev.next_upstream_timeout, NGX_CONF_UNSET_MSEC, 0);
+        // SAFETY: `ngx_pagesize` is initialized before the configuration is parsed.
+        merge_value(&mut conf.buffer_size, prev.buffer_size, NGX_CONF_UNSET_SIZE, unsafe { ngx_pagesize });
+
+        if conf.next_upstream == 0 {
+            conf.next_upstream = if prev.next_upstream == 0 {
+                (NGX_CONF_BITMASK_SET | NGX_HTTP_UPSTREAM_FT_ERROR | NGX_HTTP_UPSTREAM_FT_TIMEOUT) as ngx_uint_t
+            } else {
+                prev.next_upstream
+            };
+        }
+
+        if conf.next_upstream & NGX_HTTP_UPSTREAM_FT_OFF as ngx_uint_t != 0 {
+            conf.next_upstream = (NGX_CONF_BITMASK_SET | NGX_HTTP_UPSTREAM_FT_OFF) as ngx_uint_t;
+        }
+
+        if conf.upstream.is_null() {
+            conf.upstream = prev.upstream;
+        }
+    }
+}
+
+fn merge_value<T: Copy + PartialEq>(conf: &mut T, prev: T, unset: T, default: T) {
+    if *conf == unset {
+        *conf = if prev == unset { default } else { prev };
+    }
+}
+
+impl Request {
+    /// Create an upstream for this request, using the protocol callbacks of `U`.
+    ///
+    /// Response buffers are tagged with `module`. Once created, the upstream is started
+    /// with [`Request::upstream_init`].
+    pub fn upstream_create<U: Upstream>(&mut self, module: &ngx_module_t, conf: &UpstreamConf) -> Status {
+        let r = self as *mut Request as *mut ngx_http_request_t;
+        unsafe {
+            if ngx_http_upstream_create(r) != NGX_OK as ngx_int_t {
+                return ERROR;
+            }
+
+            let u = (*r).upstream;
+            (*u).conf = conf.as_ngx_upstream_conf();
+            (*u).output.tag = module as *const ngx_module_t as ngx_buf_tag_t;
+
+            (*u).create_request = Some(U::create_request);
+            (*u).reinit_request = Some(U::reinit_request);
+            (*u).process_header = Some(U::process_header);
+            (*u).abort_request = Some(U::abort_request);
+            (*u).finalize_request = Some(U::finalize_request);
+
+            (*u).input_filter_init = Some(U::input_filter_init);
+            (*u).input_filter = Some(U::input_filter);
+            (*u).input_filter_ctx = r as *mut c_void;
+        }
+
+        OK
+    }
+
+    /// Read the client request body (if any) and start the upstream request.
+    ///
+    /// The handler should return the result of this function.
+    pub fn upstream_init(&mut self) -> Status {
+        let r = self as *mut Request as *mut ngx_http_request_t;
+        let rc = unsafe { ngx_http_read_client_request_body(r, Some(ngx_http_upstream_init)) };
+        if rc >= NGX_HTTP_SPECIAL_RESPONSE as ngx_int_t {
+            return Status(rc);
+        }
+
+        DONE
+    }
+}
+
+/// Read-only view of the [upstream] state of a proxied request.
+///
+/// [upstream]: https://nginx.org/en/docs/dev/development_guide.html#http_load_balancing
+#[repr(transparent)]
+pub struct UpstreamInfo(ngx_http_upstream_t);
+
+impl UpstreamInfo {
+    /// Upstream response status (e.g. `200`), or `0` if no response header was received yet.
+    pub fn status(&self) -> ngx_uint_t {
+        self.0.headers_in.status_n
+    }
+
+    /// Address of the selected upstream server in text form.
+    pub fn peer_name(&self) -> Option<&NgxStr> {
+        if self.0.peer.name.is_null() {
+            return None;
+        }
+        unsafe { Some(NgxStr::from_ngx_str(*self.0.peer.name)) }
+    }
+
+    /// Address of the selected upstream server.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        unsafe { sockaddr_to_socket_addr(self.0.peer.sockaddr) }
+    }
+
+    /// Iterate over the upstream response headers.
+    pub fn headers(&self) -> HeadersIter {
+        unsafe { HeadersIter::from_ngx_list(&self.0.headers_in.headers) }
+    }
+
+    /// Value of the first upstream response header called `name` (ignoring case).
+    pub fn header(&self, name: &str) -> Option<&NgxStr> {
+        find_header(self.headers(), name)
+    }
+
+    fn state(&self) -> Option<&ngx_http_upstream_state_t> {
+        unsafe { self.0.state.as_ref() }
+    }
+
+    /// Time spent establishing a connection with the upstream server.
+    pub fn connect_time(&self) -> Option<Duration> {
+        self.state().and_then(|state| msec_to_duration(state.connect_time))
+    }
+
+    /// Time until the upstream response header was received.
+    pub fn header_time(&self) -> Option<Duration> {
+        self.state().and_then(|state| msec_to_duration(state.header_time))
+    }
+
+    /// Time until the upstream response was received.
+    ///
+    /// This is only available once the upstream request is complete (e.g. in the log phase), see
+    /// [`UpstreamInfo::elapsed`] for a request in progress.
+    pub fn response_time(&self) -> Option<Duration> {
+        self.state().and_then(|state| msec_to_duration(state.response_time))
+    }
+
+    /// Time since the request to the current upstream server was started.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.state()?;
+        let msec = current_msec().wrapping_sub(self.0.start_time);
+        Some(Duration::from_millis(msec as u64))
+    }
+
+    /// Number of bytes received from the upstream server.
+    pub fn bytes_received(&self) -> usize {
+        self.state().map_or(0, |state| state.bytes_received as usize)
+    }
+}
+
+fn msec_to_duration(msec: ngx_msec_t) -> Option<Duration> {
+    if msec == ngx_msec_t::max_value() {
+        return None;
+    }
+    Some(Duration::from_millis(msec as u64))
+}
+
+impl Request {
+    /// Upstream state, if this request is proxied.
+    pub fn upstream(&self) -> Option<&UpstreamInfo> {
+        let r = self as *const Request as *const ngx_http_request_t;
+        unsafe { (*r).upstream.cast::<UpstreamInfo>().as_ref() }
+    }
+}
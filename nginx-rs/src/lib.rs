@@ -1,7 +1,12 @@
 pub mod http;
 pub mod bindings;
 pub mod core;
+pub mod event;
 pub mod log;
+#[cfg(feature = "mail")]
+pub mod mail;
+#[cfg(feature = "stream")]
+pub mod stream;
 
 /// Define modules exported by this library.
 ///
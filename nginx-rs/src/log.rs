@@ -23,7 +23,7 @@ macro_rules! ngx_log_debug {
 #[macro_export]
 macro_rules! ngx_log_debug_http {
     ( $request:expr, $($arg:tt)* ) => {
-        let log = unsafe { (*$request.connection()).log };
+        let log = $request.connection().log();
         $crate::ngx_log_debug!(NGX_LOG_DEBUG_HTTP, log, $($arg)*);
     }
 }
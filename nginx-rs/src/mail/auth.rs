@@ -0,0 +1,197 @@
+use crate::bindings::*;
+use crate::core::*;
+use crate::http::{unescape, Request, HTTP_OK};
+use crate::mail::MailProtocol;
+
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+/// In-process authentication of mail sessions.
+///
+/// Nginx authenticates mail sessions by sending a request to the [`auth_http`] server. Instead of
+/// a separate server, [`mail_auth_http_handler`] answers these requests from a location of the
+/// same Nginx, so that sessions are authenticated by a Rust function:
+///
+/// ```ignore
+/// mail {
+///     auth_http 127.0.0.1:8025/auth;
+///     ...
+/// }
+///
+/// http {
+///     server {
+///         listen 127.0.0.1:8025;
+///         location = /auth {
+///             my_mail_auth;  # sets `clcf->handler` to `mail_auth_http_handler::<MyAuth>`
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`auth_http`]: https://nginx.org/en/docs/mail/ngx_mail_auth_http_module.html
+pub trait MailAuth {
+    /// Authenticate a session, returning the backend server to proxy it to or a rejection.
+    fn authenticate(request: &MailAuthRequest) -> MailAuthResponse;
+}
+
+/// Authentication request of a mail session, as sent by the `auth_http` client.
+pub struct MailAuthRequest<'a>(&'a Request);
+
+impl<'a> MailAuthRequest<'a> {
+    /// The underlying HTTP request.
+    pub fn request(&self) -> &Request {
+        self.0
+    }
+
+    /// Raw value of the `name` request header (e.g. `Auth-SSL-Verify`).
+    pub fn header(&self, name: &str) -> Option<&NgxStr> {
+        self.0.header_in(name)
+    }
+
+    /// Decode an escaped header value, allocating from the request pool if needed.
+    fn unescaped(&self, name: &str) -> Option<&NgxStr> {
+        let value = self.header(name)?;
+        unescape(&mut self.0.pool(), value.as_bytes(), false)
+    }
+
+    /// Authentication method (`plain`, `login`, `apop`, `cram-md5`, `external` or `none`).
+    pub fn method(&self) -> Option<&NgxStr> {
+        self.header("Auth-Method")
+    }
+
+    /// Mail protocol of the session.
+    pub fn protocol(&self) -> Option<MailProtocol> {
+        match self.header("Auth-Protocol")?.as_bytes() {
+            b"pop3" => Some(MailProtocol::Pop3),
+            b"imap" => Some(MailProtocol::Imap),
+            b"smtp" => Some(MailProtocol::Smtp),
+            _ => None,
+        }
+    }
+
+    /// User name given by the client.
+    pub fn user(&self) -> Option<&NgxStr> {
+        self.unescaped("Auth-User")
+    }
+
+    /// Password given by the client (or digest, for the `apop` and `cram-md5` methods).
+    pub fn password(&self) -> Option<&NgxStr> {
+        self.unescaped("Auth-Pass")
+    }
+
+    /// Salt of the `apop` and `cram-md5` methods.
+    pub fn salt(&self) -> Option<&NgxStr> {
+        self.header("Auth-Salt")
+    }
+
+    /// Number of authentication attempts in this session, starting at 1.
+    pub fn login_attempt(&self) -> ngx_uint_t {
+        self.header("Auth-Login-Attempt")
+            .and_then(|attempt| attempt.to_str().ok()?.parse().ok())
+            .unwrap_or(1)
+    }
+
+    /// Client address.
+    pub fn client_ip(&self) -> Option<IpAddr> {
+        self.header("Client-IP")?.to_str().ok()?.parse().ok()
+    }
+
+    /// Client host name, if `resolver` is configured in the `mail` block.
+    pub fn client_host(&self) -> Option<&NgxStr> {
+        self.header("Client-Host")
+    }
+
+    /// SMTP `HELO`/`EHLO` argument.
+    pub fn smtp_helo(&self) -> Option<&NgxStr> {
+        self.unescaped("Auth-SMTP-Helo")
+    }
+
+    /// SMTP `MAIL FROM` argument.
+    pub fn smtp_from(&self) -> Option<&NgxStr> {
+        self.unescaped("Auth-SMTP-From")
+    }
+
+    /// SMTP `RCPT TO` argument.
+    pub fn smtp_to(&self) -> Option<&NgxStr> {
+        self.unescaped("Auth-SMTP-To")
+    }
+}
+
+/// Result of [`MailAuth::authenticate`].
+pub enum MailAuthResponse {
+    /// Proxy the session to `server`.
+    ///
+    /// The backend is logged in as `user` with `password`, or with the credentials given by the
+    /// client if they're `None`. A password is required for the `apop`, `cram-md5` and
+    /// `external` methods, since the client doesn't send it. Control characters are removed
+    /// from the credentials, as they can't be sent in a header.
+    Proxy { server: SocketAddr, user: Option<String>, password: Option<String> },
+    /// Reject the authentication attempt with `message`.
+    ///
+    /// The client may try again after `wait` if it's set, otherwise the connection is closed.
+    /// For SMTP, `error_code` replaces the default `535 5.7.0` code.
+    Reject { message: String, wait: Option<Duration>, error_code: Option<String> },
+}
+
+impl MailAuthResponse {
+    /// Proxy the session to `server`, with the credentials given by the client.
+    pub fn proxy(server: SocketAddr) -> MailAuthResponse {
+        MailAuthResponse::Proxy { server, user: None, password: None }
+    }
+
+    /// Reject the session with `message`, closing the connection.
+    pub fn reject(message: &str) -> MailAuthResponse {
+        MailAuthResponse::Reject { message: message.to_string(), wait: None, error_code: None }
+    }
+
+    /// Response headers, as expected by the `auth_http` client.
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        match self {
+            MailAuthResponse::Proxy { server, user, password } => {
+                let mut headers = vec![
+                    ("Auth-Status", String::from("OK")),
+                    ("Auth-Server", server.ip().to_string()),
+                    ("Auth-Port", server.port().to_string()),
+                ];
+                headers.extend(user.iter().map(|user| ("Auth-User", sanitize(user))));
+                headers.extend(password.iter().map(|password| ("Auth-Pass", sanitize(password))));
+                headers
+            },
+            MailAuthResponse::Reject { message, wait, error_code } => {
+                let mut headers = vec![("Auth-Status", sanitize(message))];
+                headers.extend(wait.iter().map(|wait| ("Auth-Wait", wait.as_secs().to_string())));
+                headers.extend(error_code.iter().map(|code| ("Auth-Error-Code", sanitize(code))));
+                headers
+            },
+        }
+    }
+}
+
+/// Remove characters which can't be sent in a header value.
+fn sanitize(value: &str) -> String {
+    value.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// HTTP content handler answering `auth_http` requests with [`MailAuth::authenticate`].
+pub unsafe extern "C" fn mail_auth_http_handler<A: MailAuth>(r: *mut ngx_http_request_t) -> ngx_int_t {
+    let request = Request::from_ngx_http_request(r);
+
+    if !request.discard_request_body().is_ok() {
+        return NGX_HTTP_INTERNAL_SERVER_ERROR as ngx_int_t;
+    }
+
+    let response = A::authenticate(&MailAuthRequest(request));
+
+    for (key, value) in response.headers() {
+        if request.add_header_out(key, &value).is_err() {
+            return NGX_HTTP_INTERNAL_SERVER_ERROR as ngx_int_t;
+        }
+    }
+
+    // The result is only given in the headers
+    request.set_status(HTTP_OK);
+    request.set_content_length_n(0);
+    (*r).set_header_only(1);
+
+    request.send_header().into()
+}
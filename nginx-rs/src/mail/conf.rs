@@ -0,0 +1,13 @@
+use crate::bindings::*;
+
+use std::os::raw::c_void;
+
+pub unsafe fn ngx_mail_conf_get_module_main_conf(cf: *mut ngx_conf_t, module: &ngx_module_t) -> *mut c_void {
+    let mail_conf_ctx = (*cf).ctx as *mut ngx_mail_conf_ctx_t;
+    *(*mail_conf_ctx).main_conf.add(module.ctx_index)
+}
+
+pub unsafe fn ngx_mail_conf_get_module_srv_conf(cf: *mut ngx_conf_t, module: &ngx_module_t) -> *mut c_void {
+    let mail_conf_ctx = (*cf).ctx as *mut ngx_mail_conf_ctx_t;
+    *(*mail_conf_ctx).srv_conf.add(module.ctx_index)
+}
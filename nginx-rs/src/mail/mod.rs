@@ -0,0 +1,9 @@
+mod auth;
+mod conf;
+mod module;
+mod session;
+
+pub use auth::*;
+pub use conf::*;
+pub use module::*;
+pub use session::*;
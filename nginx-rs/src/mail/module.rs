@@ -0,0 +1,36 @@
+use crate::bindings::*;
+use crate::core::*;
+use crate::http::Merge;
+
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+/// Mail module, configured in the `mail {}` block.
+///
+/// The module context is an `ngx_mail_module_t` (with a null `protocol` for modules which don't
+/// implement a mail protocol), whose fields reference the functions of this trait.
+pub trait MailModule {
+    type MainConf: Merge + Default;
+    type SrvConf: Merge + Default;
+
+    unsafe extern "C" fn create_main_conf(cf: *mut ngx_conf_t) -> *mut c_void {
+        let mut pool = Pool::from_ngx_pool((*cf).pool);
+        pool.allocate::<Self::MainConf>(Default::default()) as *mut c_void
+    }
+
+    unsafe extern "C" fn init_main_conf(_cf: *mut ngx_conf_t, _conf: *mut c_void) -> *mut c_char {
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn create_srv_conf(cf: *mut ngx_conf_t) -> *mut c_void {
+        let mut pool = Pool::from_ngx_pool((*cf).pool);
+        pool.allocate::<Self::SrvConf>(Default::default()) as *mut c_void
+    }
+
+    unsafe extern "C" fn merge_srv_conf(_cf: *mut ngx_conf_t, prev: *mut c_void, conf: *mut c_void) -> *mut c_char {
+        let prev = &mut *(prev as *mut Self::SrvConf);
+        let conf = &mut *(conf as *mut Self::SrvConf);
+        conf.merge(prev);
+        ptr::null_mut()
+    }
+}
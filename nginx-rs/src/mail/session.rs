@@ -0,0 +1,111 @@
+use crate::bindings::*;
+use crate::core::*;
+
+use std::os::raw::c_void;
+
+/// Mail protocol of a session.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MailProtocol {
+    Pop3,
+    Imap,
+    Smtp,
+}
+
+/// Wrapper for a mail proxy session (`ngx_mail_session_t`).
+#[repr(transparent)]
+pub struct MailSession(ngx_mail_session_t);
+
+impl MailSession {
+    /// Create a [`MailSession`] from an [`ngx_mail_session_t`].
+    pub unsafe fn from_ngx_mail_session<'a>(s: *mut ngx_mail_session_t) -> &'a mut MailSession {
+        // SAFETY: The caller has provided a valid non-null pointer to a valid `ngx_mail_session_t`
+        // which shares the same representation as `MailSession`.
+        &mut *s.cast::<MailSession>()
+    }
+
+    /// Pointer to the underlying `ngx_mail_session_t`.
+    pub fn as_ngx_mail_session(&self) -> *mut ngx_mail_session_t {
+        &self.0 as *const ngx_mail_session_t as *mut ngx_mail_session_t
+    }
+
+    /// Client [connection].
+    ///
+    /// [connection]: https://nginx.org/en/docs/dev/development_guide.html#connection
+    pub fn connection(&self) -> &Connection {
+        // SAFETY: Every session has a valid client connection.
+        unsafe { Connection::from_ngx_connection(self.0.connection) }
+    }
+
+    /// Session pool, the pool of the client connection.
+    pub fn pool(&self) -> Pool {
+        // SAFETY: The connection pool is created before the session.
+        unsafe { Pool::from_ngx_pool((*self.0.connection).pool) }
+    }
+
+    /// Mail protocol of the session.
+    pub fn protocol(&self) -> MailProtocol {
+        match self.0.protocol() {
+            NGX_MAIL_IMAP_PROTOCOL => MailProtocol::Imap,
+            NGX_MAIL_SMTP_PROTOCOL => MailProtocol::Smtp,
+            _ => MailProtocol::Pop3,
+        }
+    }
+
+    /// User name given by the client (empty until authentication).
+    pub fn login(&self) -> &NgxStr {
+        unsafe { session_str(self.0.login) }
+    }
+
+    /// Password given by the client (empty until authentication).
+    pub fn password(&self) -> &NgxStr {
+        unsafe { session_str(self.0.passwd) }
+    }
+
+    /// Number of authentication attempts in this session.
+    pub fn login_attempt(&self) -> ngx_uint_t {
+        self.0.login_attempt
+    }
+
+    /// SMTP `HELO`/`EHLO` argument (empty until it is sent, and for other protocols).
+    pub fn smtp_helo(&self) -> &NgxStr {
+        unsafe { session_str(self.0.smtp_helo) }
+    }
+
+    /// SMTP `MAIL FROM` argument (empty until it is sent, and for other protocols).
+    pub fn smtp_from(&self) -> &NgxStr {
+        unsafe { session_str(self.0.smtp_from) }
+    }
+
+    /// SMTP `RCPT TO` argument (empty until it is sent, and for other protocols).
+    pub fn smtp_to(&self) -> &NgxStr {
+        unsafe { session_str(self.0.smtp_to) }
+    }
+
+    /// Module main configuration.
+    pub fn get_module_main_conf(&self, module: &ngx_module_t) -> *mut c_void {
+        unsafe { *self.0.main_conf.add(module.ctx_index) }
+    }
+
+    /// Module server configuration.
+    pub fn get_module_srv_conf(&self, module: &ngx_module_t) -> *mut c_void {
+        unsafe { *self.0.srv_conf.add(module.ctx_index) }
+    }
+
+    /// Module context, or null if it isn't set.
+    pub fn get_module_ctx(&self, module: &ngx_module_t) -> *mut c_void {
+        unsafe { *self.0.ctx.add(module.ctx_index) }
+    }
+
+    /// Set the module context.
+    pub fn set_module_ctx(&mut self, module: &ngx_module_t, ctx: *mut c_void) {
+        unsafe { *self.0.ctx.add(module.ctx_index) = ctx };
+    }
+}
+
+/// Session string, which is zeroed (with a null `data`) until it's set.
+unsafe fn session_str<'a>(str: ngx_str_t) -> &'a NgxStr {
+    if str.data.is_null() {
+        return "".into();
+    }
+    NgxStr::from_ngx_str(str)
+}
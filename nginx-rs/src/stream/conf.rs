@@ -0,0 +1,22 @@
+use crate::bindings::*;
+
+use std::os::raw::c_void;
+use std::ptr;
+
+pub unsafe fn ngx_stream_conf_get_module_main_conf(cf: *mut ngx_conf_t, module: &ngx_module_t) -> *mut c_void {
+    let stream_conf_ctx = (*cf).ctx as *mut ngx_stream_conf_ctx_t;
+    *(*stream_conf_ctx).main_conf.add(module.ctx_index)
+}
+
+pub unsafe fn ngx_stream_conf_get_module_srv_conf(cf: *mut ngx_conf_t, module: &ngx_module_t) -> *mut c_void {
+    let stream_conf_ctx = (*cf).ctx as *mut ngx_stream_conf_ctx_t;
+    *(*stream_conf_ctx).srv_conf.add(module.ctx_index)
+}
+
+pub unsafe fn ngx_stream_cycle_get_module_main_conf(cycle: *mut ngx_cycle_t, module: &ngx_module_t) -> *mut c_void {
+    let stream_conf_ctx = *(*cycle).conf_ctx.add(ngx_stream_module.index) as *mut ngx_stream_conf_ctx_t;
+    if stream_conf_ctx.is_null() {
+        return ptr::null_mut();
+    }
+    *(*stream_conf_ctx).main_conf.add(module.ctx_index)
+}
@@ -0,0 +1,86 @@
+use crate::bindings::*;
+
+/// Direction of the data passed to a stream filter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StreamDirection {
+    /// Data received from the client, sent to the upstream.
+    FromClient,
+    /// Data received from the upstream, sent to the client.
+    FromUpstream,
+}
+
+impl StreamDirection {
+    /// Direction from the `from_upstream` argument of an `ngx_stream_filter_pt`.
+    pub fn from_upstream(from_upstream: ngx_uint_t) -> StreamDirection {
+        if from_upstream != 0 {
+            StreamDirection::FromUpstream
+        } else {
+            StreamDirection::FromClient
+        }
+    }
+}
+
+/// Define a static stream filter, inspecting the data proxied in both directions.
+///
+/// Filters are expected to take a [`StreamSession`], the chain of buffers (`*mut ngx_chain_t`,
+/// which may be null) and the [`StreamDirection`], and return a [`Status`]. The chain is passed to
+/// the next filter if the status is `OK`, otherwise the status is returned (e.g. `ERROR` closes
+/// the session).
+///
+/// The filter is registered with [`register_stream_filter`], usually in
+/// [`StreamModule::postconfiguration`]:
+///
+/// ```ignore
+/// stream_filter!(ngx_stream_count_filter, |session: &mut StreamSession, chain, direction| {
+///     let n: usize = unsafe { iter_chain(chain) }.map(|buf| buf.len()).sum();
+///     // ...
+///     OK
+/// });
+///
+/// unsafe extern "C" fn postconfiguration(_cf: *mut ngx_conf_t) -> ngx_int_t {
+///     register_stream_filter!(ngx_stream_count_filter);
+///     OK.into()
+/// }
+/// ```
+///
+/// [`StreamSession`]: crate::stream::StreamSession
+/// [`Status`]: crate::core::Status
+/// [`register_stream_filter`]: crate::register_stream_filter
+/// [`StreamModule::postconfiguration`]: crate::stream::StreamModule::postconfiguration
+#[macro_export]
+macro_rules! stream_filter {
+    ( $name: ident, $handler: expr ) => {
+        // Next filter in the chain, set on registration
+        #[allow(non_snake_case)]
+        mod $name {
+            pub static mut NEXT: $crate::bindings::ngx_stream_filter_pt = None;
+        }
+
+        #[no_mangle]
+        extern "C" fn $name(s: *mut ngx_stream_session_t, chain: *mut ngx_chain_t, from_upstream: ngx_uint_t) -> ngx_int_t {
+            let session = unsafe { $crate::stream::StreamSession::from_ngx_stream_session(s) };
+            let direction = $crate::stream::StreamDirection::from_upstream(from_upstream);
+            let status: Status = $handler(session, chain, direction);
+            if status != $crate::core::OK {
+                return status.0;
+            }
+
+            match unsafe { $name::NEXT } {
+                Some(next) => unsafe { next(s, chain, from_upstream) },
+                None => $crate::bindings::NGX_OK as ngx_int_t,
+            }
+        }
+    };
+}
+
+/// Register a filter defined with [`stream_filter`](crate::stream_filter) at the top of the
+/// stream filter chain (`ngx_stream_top_filter`).
+#[macro_export]
+macro_rules! register_stream_filter {
+    ( $name: ident ) => {
+        unsafe {
+            $name::NEXT = $crate::bindings::ngx_stream_top_filter;
+            $crate::bindings::ngx_stream_top_filter = Some($name);
+        }
+    };
+}
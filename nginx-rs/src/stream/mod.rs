@@ -0,0 +1,15 @@
+mod conf;
+mod filter;
+mod module;
+mod preread;
+mod session;
+mod status;
+mod variable;
+
+pub use conf::*;
+pub use filter::*;
+pub use module::*;
+pub use preread::*;
+pub use session::*;
+pub use status::*;
+pub use variable::*;
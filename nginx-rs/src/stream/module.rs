@@ -0,0 +1,91 @@
+use crate::bindings::*;
+use crate::core::*;
+use crate::http::Merge;
+use crate::stream::*;
+
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+/// Stream (TCP/UDP) module, configured in the `stream {}` block.
+///
+/// The module context is an `ngx_stream_module_t`, whose fields reference the functions of this
+/// trait (e.g. `create_srv_conf: Some(Module::create_srv_conf)`).
+pub trait StreamModule {
+    type MainConf: Merge + Default;
+    type SrvConf: Merge + Default;
+
+    unsafe extern "C" fn preconfiguration(_cf: *mut ngx_conf_t) -> ngx_int_t {
+        OK.into()
+    }
+
+    unsafe extern "C" fn postconfiguration(_cf: *mut ngx_conf_t) -> ngx_int_t {
+        OK.into()
+    }
+
+    unsafe extern "C" fn create_main_conf(cf: *mut ngx_conf_t) -> *mut c_void {
+        let mut pool = Pool::from_ngx_pool((*cf).pool);
+        pool.allocate::<Self::MainConf>(Default::default()) as *mut c_void
+    }
+
+    unsafe extern "C" fn init_main_conf(_cf: *mut ngx_conf_t, _conf: *mut c_void) -> *mut c_char {
+        ptr::null_mut()
+    }
+
+    unsafe extern "C" fn create_srv_conf(cf: *mut ngx_conf_t) -> *mut c_void {
+        let mut pool = Pool::from_ngx_pool((*cf).pool);
+        pool.allocate::<Self::SrvConf>(Default::default()) as *mut c_void
+    }
+
+    unsafe extern "C" fn merge_srv_conf(_cf: *mut ngx_conf_t, prev: *mut c_void, conf: *mut c_void) -> *mut c_char {
+        let prev = &mut *(prev as *mut Self::SrvConf);
+        let conf = &mut *(conf as *mut Self::SrvConf);
+        conf.merge(prev);
+        ptr::null_mut()
+    }
+}
+
+/// Phases of [stream session processing] accepting handlers.
+///
+/// The content phase is handled by a single handler per server, set with
+/// [`set_stream_content_handler`].
+///
+/// [stream session processing]: https://nginx.org/en/docs/stream/stream_processing.html
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StreamPhase {
+    PostAccept,
+    Preaccess,
+    Access,
+    Ssl,
+    Preread,
+    Log,
+}
+
+impl StreamPhase {
+    fn as_ngx_stream_phase(self) -> ngx_stream_phases {
+        match self {
+            StreamPhase::PostAccept => ngx_stream_phases_NGX_STREAM_POST_ACCEPT_PHASE,
+            StreamPhase::Preaccess => ngx_stream_phases_NGX_STREAM_PREACCESS_PHASE,
+            StreamPhase::Access => ngx_stream_phases_NGX_STREAM_ACCESS_PHASE,
+            StreamPhase::Ssl => ngx_stream_phases_NGX_STREAM_SSL_PHASE,
+            StreamPhase::Preread => ngx_stream_phases_NGX_STREAM_PREREAD_PHASE,
+            StreamPhase::Log => ngx_stream_phases_NGX_STREAM_LOG_PHASE,
+        }
+    }
+}
+
+/// Add a handler to a phase, usually in [`StreamModule::postconfiguration`].
+///
+/// Handlers are defined with [`stream_session_handler`](crate::stream_session_handler).
+pub unsafe fn add_stream_phase_handler(cf: *mut ngx_conf_t, phase: StreamPhase, handler: ngx_stream_handler_pt) -> Result<(), ()> {
+    let cmcf = ngx_stream_conf_get_module_main_conf(cf, &ngx_stream_core_module) as *mut ngx_stream_core_main_conf_t;
+    let handlers = NgxArray::<ngx_stream_handler_pt>::from_ngx_array(&mut (*cmcf).phases[phase.as_ngx_stream_phase() as usize].handlers);
+    handlers.push(handler).map(|_| ()).ok_or(())
+}
+
+/// Set the content handler of the current server, usually from a directive handler.
+///
+/// The content handler is responsible for finalizing the session.
+pub unsafe fn set_stream_content_handler(cf: *mut ngx_conf_t, handler: ngx_stream_content_handler_pt) {
+    let cscf = ngx_stream_conf_get_module_srv_conf(cf, &ngx_stream_core_module) as *mut ngx_stream_core_srv_conf_t;
+    (*cscf).handler = handler;
+}
@@ -0,0 +1,85 @@
+use crate::bindings::*;
+use crate::core::*;
+use crate::stream::*;
+
+use std::slice;
+
+/// Result of inspecting the data read at the start of a connection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Preread {
+    /// More data is needed.
+    Incomplete,
+    /// The data was recognized, end the preread phase.
+    Done,
+    /// The data wasn't recognized, let other preread handlers inspect it.
+    Declined,
+}
+
+impl StreamSession {
+    /// Data read from the client so far, during the preread phase.
+    ///
+    /// The data is kept in the connection buffer and is forwarded once the session is proxied.
+    pub fn preread_buffer(&self) -> Option<&[u8]> {
+        unsafe {
+            let b = (*self.connection().as_ngx_connection()).buffer;
+            if b.is_null() {
+                return None;
+            }
+
+            Some(slice::from_raw_parts((*b).pos, (*b).last.offset_from((*b).pos) as usize))
+        }
+    }
+
+    /// Inspect the first bytes of a TCP connection from a preread phase handler, like
+    /// `ngx_stream_ssl_preread_module`.
+    ///
+    /// `inspect` is called with the data read so far, each time more data is read, until it
+    /// returns [`Preread::Done`] or [`Preread::Declined`]. The handler should return the result of
+    /// this function:
+    ///
+    /// ```ignore
+    /// stream_session_handler!(ngx_stream_proto_preread_handler, |session: &mut StreamSession| {
+    ///     session.preread(|session, data| {
+    ///         if data.len() < 4 {
+    ///             return Preread::Incomplete;
+    ///         }
+    ///         let ctx = session.pool().allocate(Ctx { proto: detect(data) });
+    ///         session.set_module_ctx(unsafe { &ngx_stream_proto_module }, ctx as *mut c_void);
+    ///         Preread::Done
+    ///     })
+    /// });
+    /// ```
+    ///
+    /// UDP sessions are declined. Once the preread buffer (`preread_buffer_size`) is full,
+    /// incomplete data is declined too.
+    pub fn preread<F>(&mut self, inspect: F) -> Status
+    where
+        F: FnOnce(&mut StreamSession, &[u8]) -> Preread,
+    {
+        let c = self.connection().as_ngx_connection();
+
+        unsafe {
+            if (*c).type_ != NGX_RS_SOCK_STREAM {
+                return DECLINED;
+            }
+
+            let b = (*c).buffer;
+            if b.is_null() {
+                return AGAIN;
+            }
+
+            // SAFETY: The buffer isn't modified while the handler runs.
+            let data = slice::from_raw_parts((*b).pos, (*b).last.offset_from((*b).pos) as usize);
+
+            match inspect(self, data) {
+                Preread::Done => OK,
+                Preread::Declined => DECLINED,
+                Preread::Incomplete if (*b).last == (*b).end => {
+                    crate::ngx_log_debug!(NGX_LOG_DEBUG_STREAM, (*c).log, "stream preread buffer full");
+                    DECLINED
+                },
+                Preread::Incomplete => AGAIN,
+            }
+        }
+    }
+}
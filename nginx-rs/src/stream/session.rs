@@ -0,0 +1,139 @@
+use crate::bindings::*;
+use crate::core::*;
+use crate::stream::*;
+
+use std::os::raw::c_void;
+
+/// Define a static stream session handler, for a phase or the content of a server.
+///
+/// Handlers are expected to take a single [`StreamSession`] argument and return a [`Status`].
+/// Phase handlers return `OK` to go to the next phase, `DECLINED` to go to the next handler,
+/// `AGAIN` or `DONE` to wait, or a [`StreamStatus`] to finalize the session.
+///
+/// ```ignore
+/// stream_session_handler!(ngx_stream_hello_access_handler, |session: &mut StreamSession| {
+///     if session.connection().remote_addr().map_or(true, |addr| !addr.ip().is_loopback()) {
+///         return STREAM_FORBIDDEN.into();
+///     }
+///
+///     DECLINED
+/// });
+/// ```
+///
+/// With `content` (e.g. `stream_session_handler!(content ngx_stream_echo_handler, ...)`), the
+/// handler is defined as a content handler: the session is finalized with the returned status,
+/// unless it's `AGAIN` or `DONE`. `OK` finalizes it with [`STREAM_OK`], and other negative
+/// statuses (e.g. `ERROR` or `DECLINED`) with [`STREAM_INTERNAL_SERVER_ERROR`].
+#[macro_export]
+macro_rules! stream_session_handler {
+    ( content $name: ident, $handler: expr ) => {
+        #[no_mangle]
+        extern "C" fn $name(s: *mut ngx_stream_session_t) {
+            let session = unsafe { $crate::stream::StreamSession::from_ngx_stream_session(s) };
+            let status: Status = $handler(&mut *session);
+            if status == $crate::core::AGAIN || status == $crate::core::DONE {
+                return;
+            }
+
+            let code = if status == $crate::core::OK {
+                $crate::bindings::NGX_STREAM_OK as ngx_uint_t
+            } else if status.0 < 0 {
+                $crate::bindings::NGX_STREAM_INTERNAL_SERVER_ERROR as ngx_uint_t
+            } else {
+                status.0 as ngx_uint_t
+            };
+            session.finalize(code);
+        }
+    };
+    ( $name: ident, $handler: expr ) => {
+        #[no_mangle]
+        extern "C" fn $name(s: *mut ngx_stream_session_t) -> ngx_int_t {
+            let status: Status = $handler(unsafe { &mut $crate::stream::StreamSession::from_ngx_stream_session(s) });
+            status.0
+        }
+    };
+}
+
+/// Wrapper for a stream session (`ngx_stream_session_t`), the state of a TCP connection or
+/// UDP datagram exchange.
+#[repr(transparent)]
+pub struct StreamSession(ngx_stream_session_t);
+
+impl StreamSession {
+    /// Create a [`StreamSession`] from an [`ngx_stream_session_t`].
+    pub unsafe fn from_ngx_stream_session<'a>(s: *mut ngx_stream_session_t) -> &'a mut StreamSession {
+        // SAFETY: The caller has provided a valid non-null pointer to a valid `ngx_stream_session_t`
+        // which shares the same representation as `StreamSession`.
+        &mut *s.cast::<StreamSession>()
+    }
+
+    /// Pointer to the underlying `ngx_stream_session_t`.
+    pub fn as_ngx_stream_session(&self) -> *mut ngx_stream_session_t {
+        &self.0 as *const ngx_stream_session_t as *mut ngx_stream_session_t
+    }
+
+    /// Client [connection].
+    ///
+    /// [connection]: https://nginx.org/en/docs/dev/development_guide.html#connection
+    pub fn connection(&self) -> &Connection {
+        // SAFETY: Every session has a valid client connection.
+        unsafe { Connection::from_ngx_connection(self.0.connection) }
+    }
+
+    /// Session pool, the pool of the client connection.
+    pub fn pool(&self) -> Pool {
+        // SAFETY: The connection pool is created before the session.
+        unsafe { Pool::from_ngx_pool((*self.0.connection).pool) }
+    }
+
+    /// Module main configuration.
+    pub fn get_module_main_conf(&self, module: &ngx_module_t) -> *mut c_void {
+        unsafe { *self.0.main_conf.add(module.ctx_index) }
+    }
+
+    /// Module server configuration.
+    pub fn get_module_srv_conf(&self, module: &ngx_module_t) -> *mut c_void {
+        unsafe { *self.0.srv_conf.add(module.ctx_index) }
+    }
+
+    /// Module context, or null if it isn't set.
+    pub fn get_module_ctx(&self, module: &ngx_module_t) -> *mut c_void {
+        unsafe { *self.0.ctx.add(module.ctx_index) }
+    }
+
+    /// Set the module context.
+    pub fn set_module_ctx(&mut self, module: &ngx_module_t, ctx: *mut c_void) {
+        unsafe { *self.0.ctx.add(module.ctx_index) = ctx };
+    }
+
+    /// Is this a UDP session?
+    pub fn is_udp(&self) -> bool {
+        // SAFETY: Every session has a valid client connection.
+        unsafe { (*self.0.connection).type_ == NGX_RS_SOCK_DGRAM }
+    }
+
+    /// Number of bytes received from the client.
+    pub fn received(&self) -> usize {
+        self.0.received as usize
+    }
+
+    /// Session status, reported in the `$status` variable.
+    pub fn status(&self) -> StreamStatus {
+        StreamStatus(self.0.status)
+    }
+
+    /// Set the session status.
+    pub fn set_status(&mut self, status: StreamStatus) {
+        self.0.status = status.into();
+    }
+
+    /// Run the phase handlers of the session (`ngx_stream_core_run_phases`), e.g. after waiting.
+    pub fn run_phases(&mut self) {
+        unsafe { ngx_stream_core_run_phases(&mut self.0) };
+    }
+
+    /// Finalize and close the session with `rc` as its status.
+    pub fn finalize(&mut self, rc: ngx_uint_t) {
+        unsafe { ngx_stream_finalize_session(&mut self.0, rc) };
+    }
+}
@@ -0,0 +1,24 @@
+use crate::bindings::*;
+use crate::core::Status;
+
+/// Status of a stream session, used to finalize it and in the `$status` variable.
+pub struct StreamStatus(pub ngx_uint_t);
+
+impl Into<Status> for StreamStatus {
+    fn into(self) -> Status {
+        Status(self.0 as ngx_int_t)
+    }
+}
+
+impl Into<ngx_uint_t> for StreamStatus {
+    fn into(self) -> ngx_uint_t {
+        self.0
+    }
+}
+
+pub const STREAM_OK: StreamStatus = StreamStatus(NGX_STREAM_OK as ngx_uint_t);
+pub const STREAM_BAD_REQUEST: StreamStatus = StreamStatus(NGX_STREAM_BAD_REQUEST as ngx_uint_t);
+pub const STREAM_FORBIDDEN: StreamStatus = StreamStatus(NGX_STREAM_FORBIDDEN as ngx_uint_t);
+pub const STREAM_INTERNAL_SERVER_ERROR: StreamStatus = StreamStatus(NGX_STREAM_INTERNAL_SERVER_ERROR as ngx_uint_t);
+pub const STREAM_BAD_GATEWAY: StreamStatus = StreamStatus(NGX_STREAM_BAD_GATEWAY as ngx_uint_t);
+pub const STREAM_SERVICE_UNAVAILABLE: StreamStatus = StreamStatus(NGX_STREAM_SERVICE_UNAVAILABLE as ngx_uint_t);
@@ -0,0 +1,54 @@
+use crate::bindings::*;
+use crate::core::*;
+use crate::stream::*;
+
+/// A stream [variable] computed from the session (e.g. a protocol detected during preread).
+///
+/// [variable]: https://nginx.org/en/docs/dev/development_guide.html#http_variables
+pub trait StreamVariable {
+    /// Value of the variable, or `None` if it's not found.
+    fn get(session: &StreamSession) -> Option<&[u8]>;
+}
+
+/// Add the variable `name` (without the `$`), usually in [`StreamModule::preconfiguration`].
+///
+/// The variable isn't cached, so it can be read before and after its value is known.
+pub unsafe fn add_stream_variable<V: StreamVariable>(cf: *mut ngx_conf_t, name: &str) -> Result<(), ()> {
+    let mut pool = Pool::from_ngx_pool((*cf).pool);
+    let mut name = pool.create_ngx_str(name.as_bytes()).ok_or(())?;
+
+    let var = ngx_stream_add_variable(cf, &mut name, NGX_STREAM_VAR_NOCACHEABLE as ngx_uint_t);
+    if var.is_null() {
+        return Err(());
+    }
+
+    (*var).get_handler = Some(get_variable::<V>);
+    (*var).data = 0;
+
+    Ok(())
+}
+
+unsafe extern "C" fn get_variable<V: StreamVariable>(s: *mut ngx_stream_session_t, v: *mut ngx_stream_variable_value_t, _data: usize) -> ngx_int_t {
+    let session = StreamSession::from_ngx_stream_session(s);
+
+    let value = match V::get(session) {
+        Some(value) => value,
+        None => {
+            (*v).set_not_found(1);
+            return OK.into();
+        },
+    };
+
+    let value = match session.pool().create_ngx_str(value) {
+        Some(value) => value,
+        None => return ERROR.into(),
+    };
+
+    (*v).set_len(value.len as _);
+    (*v).set_valid(1);
+    (*v).set_no_cacheable(0);
+    (*v).set_not_found(0);
+    (*v).data = value.data;
+
+    OK.into()
+}
@@ -1,5 +1,25 @@
 #include <ngx_http.h>
 
 // Define as constants since bindgen can't parse these values
+const size_t NGX_RS_HTTP_MAIN_CONF_OFFSET = NGX_HTTP_MAIN_CONF_OFFSET;
+const size_t NGX_RS_HTTP_SRV_CONF_OFFSET = NGX_HTTP_SRV_CONF_OFFSET;
 const size_t NGX_RS_HTTP_LOC_CONF_OFFSET = NGX_HTTP_LOC_CONF_OFFSET;
 const char* NGX_RS_MODULE_SIGNATURE = NGX_MODULE_SIGNATURE;
+const int NGX_RS_AF_INET = AF_INET;
+const int NGX_RS_AF_INET6 = AF_INET6;
+const int NGX_RS_SOCK_STREAM = SOCK_STREAM;
+const int NGX_RS_SOCK_DGRAM = SOCK_DGRAM;
+
+#ifdef NGX_RS_STREAM
+#include <ngx_stream.h>
+
+const size_t NGX_RS_STREAM_MAIN_CONF_OFFSET = NGX_STREAM_MAIN_CONF_OFFSET;
+const size_t NGX_RS_STREAM_SRV_CONF_OFFSET = NGX_STREAM_SRV_CONF_OFFSET;
+#endif
+
+#ifdef NGX_RS_MAIL
+#include <ngx_mail.h>
+
+const size_t NGX_RS_MAIL_MAIN_CONF_OFFSET = NGX_MAIL_MAIN_CONF_OFFSET;
+const size_t NGX_RS_MAIL_SRV_CONF_OFFSET = NGX_MAIL_SRV_CONF_OFFSET;
+#endif